@@ -1,10 +1,8 @@
 #!/bin/bash
 mkdir -p bin
-for width in {2..15}; do
-    for height in $(seq 2 $width); do
-        SQUARE=$([ $width = $height ] && echo ",square")
-        echo "building $width x $height $SQUARE"
-        RUSTC_FLAGS="-C target-cpu=native" cargo +nightly build --release --no-default-features --features="width-$width,height-$height,unchecked,charset-english-extended$SQUARE" || exit 1
-        cp target/release/fwrf bin/fwrf-${width}x${height}
-    done
-done
\ No newline at end of file
+echo "building fwrf"
+RUSTC_FLAGS="-C target-cpu=native" cargo +nightly build --release --no-default-features --features="row-by-row,fnvmap,unchecked,charset-english-extended" || exit 1
+cp target/release/fwrf bin/fwrf
+echo "building fwrf-auto"
+RUSTC_FLAGS="-C target-cpu=native" cargo +nightly build --release --no-default-features --features="row-by-row,fnvmap,unchecked,charset-auto" || exit 1
+cp target/release/fwrf bin/fwrf-auto
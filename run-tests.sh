@@ -1,7 +1,8 @@
 #!/bin/bash
 cargo +nightly test || exit 1
-cargo +nightly test --no-default-features --features=width-5,height-5,charset-english-small,square || exit 1
-cargo +nightly test --no-default-features --features=width-6,height-4,charset-english-small || exit 1
-cargo +nightly test --no-default-features --features=width-4,height-2,charset-english-small || exit 1
+cargo +nightly test --no-default-features --features=charset-english-small,weave,fnvmap || exit 1
+cargo +nightly test --no-default-features --features=charset-english-extended,row-by-row,btreemap || exit 1
+cargo +nightly test --no-default-features --features=charset-multilingual,row-by-row,fnvmap || exit 1
+cargo +nightly test --no-default-features --features=charset-auto,row-by-row,fnvmap || exit 1
 echo
-echo "All tests completed"
\ No newline at end of file
+echo "All tests completed"
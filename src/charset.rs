@@ -1,10 +1,19 @@
-use crate::config::*;
-use crate::echar::EncodedChar;
+use super::config::*;
+use crate::echar::{EncodedChar, NULL_CHAR};
 
-#[derive(Debug,Clone,Copy,Eq,PartialEq,Default)]
+#[derive(Debug,Clone,Copy,Eq,PartialEq,Ord,PartialOrd,Hash,Default)]
 pub struct CharSet(CharSetInner);
 
 impl CharSet {
+    // Every char in the alphabet.
+    pub fn full() -> CharSet {
+        let mut res = CharSet::default();
+        for e in std::iter::successors(NULL_CHAR.inc(), |e| e.inc()) {
+            res.set(e);
+        }
+        res
+    }
+
     pub fn set(&mut self, e: EncodedChar) {
         let inner = e.inner();
         if inner >= CHAR_SET_SIZE { panic!("invalid echar to set on charset {:?}", e) }
@@ -21,4 +30,36 @@ impl CharSet {
     pub fn and(self, other: CharSet) -> CharSet {
         CharSet(self.0 & other.0)
     }
+
+    #[must_use]
+    pub fn without(self, other: CharSet) -> CharSet {
+        CharSet(self.0 & !other.0)
+    }
+
+    // The chars in the set that `EncodedChar::inc` comes to after `e`.
+    #[must_use]
+    pub fn after(self, e: EncodedChar) -> CharSet {
+        let inner = e.inner();
+        if inner >= CHAR_SET_SIZE { panic!("invalid echar to split charset at {:?}", e) }
+        CharSet(self.0 & !(CharSetInner::MAX >> (CHAR_SET_SIZE - 1 - inner)))
+    }
+
+    #[allow(dead_code)]
+    pub fn len(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    // Takes out the first char in the set, the same one `EncodedChar::inc` would come to first.
+    #[allow(dead_code)]
+    pub fn pop_first(&mut self) -> Option<EncodedChar> {
+        if self.is_empty() { return None }
+        let ranged:CharSetRanged = (self.0.trailing_zeros() as usize).try_into().unwrap();
+        self.0 &= self.0 - 1;
+        Some(ranged.into())
+    }
 }
\ No newline at end of file
@@ -1,6 +1,12 @@
 use core::convert::TryFrom;
 use core::fmt;
+#[cfg(feature = "charset-auto")]
+use std::sync::OnceLock;
 
+#[cfg(feature = "charset-auto")]
+use fnv::FnvHashMap;
+
+#[cfg(not(feature = "charset-auto"))]
 use crate::config::*;
 
 #[derive(PartialEq,Eq,PartialOrd,Ord,Copy,Clone,Hash)]
@@ -8,7 +14,7 @@ pub struct EncodedChar(u8);
 
 impl fmt::Debug for EncodedChar {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        if *self == NULL_CHAR || (self.0 as usize) < CHAR_SET_SIZE {
+        if *self == NULL_CHAR || (self.0 as usize) < alphabet_len() {
             write!(f, "E{}", (*self).into():char)
         } else {
             write!(f, "E{}", self.0)
@@ -27,16 +33,23 @@ impl EncodedChar {
         self.0.into()
     }
 
-    // Two EncodedChars "match" if either is NULL_CHAR or they are equal. 
+    // Two EncodedChars "match" if either is NULL_CHAR or they are equal. A blank can be filled with any letter, but never with a block.
     pub fn is_match(self, other: Self) -> bool {
-        self == NULL_CHAR || other == NULL_CHAR || self == other
+        self == other || (self == NULL_CHAR && other != BLOCK_CHAR) || (other == NULL_CHAR && self != BLOCK_CHAR)
     }
 
     #[must_use]
     pub fn inc(self) -> Option<Self> {
+        self.inc_below(alphabet_len())
+    }
+
+    // Like inc, for an alphabet of `len` chars. With charset-auto, looking up the alphabet's length every step adds up, so the search looks it up once and uses this.
+    #[must_use]
+    #[inline(always)]
+    pub fn inc_below(self, len: usize) -> Option<Self> {
         if self == NULL_CHAR {
             Some(Self(0))
-        } else if self.0 < (CHAR_SET_SIZE - 1) as u8 {
+        } else if (self.0 as usize) + 1 < len {
             Some(Self(self.0 + 1))
         } else {
             None
@@ -45,6 +58,8 @@ impl EncodedChar {
 }
 
 pub const NULL_CHAR:EncodedChar = EncodedChar(u8::MAX);
+// A black square in a crossword template, written '#'. It's never part of the alphabet, and is only ever read from templates.
+pub const BLOCK_CHAR:EncodedChar = EncodedChar(u8::MAX - 1);
 
 #[derive(Debug,Clone,Copy,PartialEq,Eq)]
 pub struct UnencodeableChar(pub char);
@@ -57,14 +72,193 @@ impl fmt::Display for UnencodeableChar {
 
 impl std::error::Error for UnencodeableChar {}
 
+// Non-ASCII letters need downcasing too (eg 'Σ', 'Ж'), but only when they downcase to a single char.
+pub fn downcase(value: char) -> char {
+    let mut lower = value.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(c), None) => c,
+        _ => value,
+    }
+}
+
+#[cfg(not(feature = "charset-auto"))]
+#[inline(always)]
+pub fn alphabet_len() -> usize {
+    CHAR_SET_SIZE
+}
+
+// With charset-auto the alphabet is made from the characters the wordlist actually uses, so it can only be known at runtime. It must be set exactly once, before anything is encoded.
+#[cfg(feature = "charset-auto")]
+pub const MAX_ALPHABET_LEN:usize = 128;
+
+#[cfg(feature = "charset-auto")]
+struct Alphabet {
+    chars: Vec<char>,
+    codes: FnvHashMap<char, EncodedChar>,
+}
+
+#[cfg(feature = "charset-auto")]
+static ALPHABET:OnceLock<Alphabet> = OnceLock::new();
+
+#[cfg(feature = "charset-auto")]
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum AlphabetError {
+    TooManyChars(usize),
+    AlreadySet,
+    // Line numbers start at 1.
+    BadCharsetLine(usize),
+    DuplicateChar(char),
+    UnknownAliasTarget(char),
+}
+
+#[cfg(feature = "charset-auto")]
+impl fmt::Display for AlphabetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlphabetError::TooManyChars(n) => write!(f, "The wordlist uses {} different characters, at most {} are supported", n, MAX_ALPHABET_LEN),
+            AlphabetError::AlreadySet => write!(f, "The alphabet has already been set"),
+            AlphabetError::BadCharsetLine(lineno) => write!(f, "Line {} of the charset file must be a single character, or an alias like `é = e`", lineno),
+            AlphabetError::DuplicateChar(c) => write!(f, "{:?} is in the charset file more than once", c),
+            AlphabetError::UnknownAliasTarget(c) => write!(f, "{:?} is used as an alias but is not in the charset", c),
+        }
+    }
+}
+
+#[cfg(feature = "charset-auto")]
+impl std::error::Error for AlphabetError {}
+
+// Chars are downcased and sorted, so results come out in the same order regardless of the order of the wordlist. '&' is always the null char and is never part of the alphabet.
+#[cfg(feature = "charset-auto")]
+pub fn set_alphabet(chars: impl IntoIterator<Item = char>) -> Result<usize, AlphabetError> {
+    let mut chars:Vec<char> = chars.into_iter().map(downcase).filter(|&c| c != '&').collect();
+    chars.sort_unstable();
+    chars.dedup();
+    init_alphabet(chars, Vec::new())
+}
+
+// A charset file lists one character per line, in the order they should be encoded. A line like `é = e` instead makes é an alias that is read as e, the target must be listed on its own line too. Blank lines are ignored.
+#[cfg(feature = "charset-auto")]
+pub fn parse_charset(contents: &str) -> Result<(Vec<char>, Vec<(char, char)>), AlphabetError> {
+    fn single_char(s: &str) -> Option<char> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(downcase(c)),
+            _ => None,
+        }
+    }
+
+    let mut chars = Vec::new();
+    let mut aliases = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let bad_line = AlphabetError::BadCharsetLine(i + 1);
+        if let Some((alias, target)) = line.split_once('=').filter(|_| line != "=") {
+            let alias = single_char(alias.trim()).ok_or(bad_line)?;
+            let target = single_char(target.trim()).ok_or(bad_line)?;
+            aliases.push((alias, target));
+        } else {
+            let c = single_char(line).ok_or(bad_line)?;
+            if c == '&' {
+                return Err(bad_line);
+            }
+            chars.push(c);
+        }
+    }
+    Ok((chars, aliases))
+}
+
+#[cfg(feature = "charset-auto")]
+pub fn set_alphabet_from_charset(contents: &str) -> Result<usize, AlphabetError> {
+    let (chars, aliases) = parse_charset(contents)?;
+    init_alphabet(chars, aliases)
+}
+
+// Every test shares the one alphabet, so with charset-auto it's a-z rather than whatever the first test to run happens to use.
+#[cfg(test)]
+pub fn test_alphabet() {
+    #[cfg(feature = "charset-auto")]
+    let _ = set_alphabet('a'..='z');
+}
+
+#[cfg(feature = "charset-auto")]
+fn init_alphabet(chars: Vec<char>, aliases: Vec<(char, char)>) -> Result<usize, AlphabetError> {
+    if chars.len() > MAX_ALPHABET_LEN {
+        return Err(AlphabetError::TooManyChars(chars.len()));
+    }
+    let mut codes:FnvHashMap<char, EncodedChar> = Default::default();
+    for (i, &c) in chars.iter().enumerate() {
+        if codes.insert(c, EncodedChar(i as u8)).is_some() {
+            return Err(AlphabetError::DuplicateChar(c));
+        }
+    }
+    for (alias, target) in aliases {
+        let e = *codes.get(&target).ok_or(AlphabetError::UnknownAliasTarget(target))?;
+        if codes.insert(alias, e).is_some() {
+            return Err(AlphabetError::DuplicateChar(alias));
+        }
+    }
+    let len = chars.len();
+    ALPHABET.set(Alphabet{chars, codes}).map_err(|_| AlphabetError::AlreadySet)?;
+    Ok(len)
+}
+
+#[cfg(feature = "charset-auto")]
+#[inline(always)]
+pub fn alphabet_len() -> usize {
+    ALPHABET.get().map_or(0, |a| a.chars.len())
+}
+
+#[cfg(feature = "charset-auto")]
+impl TryFrom<char> for EncodedChar {
+    type Error = UnencodeableChar;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        let value = downcase(value);
+        if value == '&' {
+            return Ok(NULL_CHAR);
+        }
+        ALPHABET.get()
+            .and_then(|a| a.codes.get(&value))
+            .copied()
+            .ok_or(UnencodeableChar(value))
+    }
+}
+
+#[cfg(feature = "charset-auto")]
+impl<const MAX:usize> From<deranged::Usize<0, MAX>> for EncodedChar {
+    fn from(value: deranged::Usize<0, MAX>) -> EncodedChar {
+        Self((value.into():usize) as u8)
+    }
+}
+
+#[cfg(feature = "charset-auto")]
+impl From<EncodedChar> for char {
+    fn from(value: EncodedChar) -> char {
+        if value == NULL_CHAR {
+            return '&';
+        }
+        if value == BLOCK_CHAR {
+            return '#';
+        }
+        ALPHABET.get()
+            .and_then(|a| a.chars.get(value.inner()))
+            .copied()
+            .unwrap_or('?')
+    }
+}
+
+#[cfg(not(feature = "charset-auto"))]
 macro_rules! encoded_char_impls {
     ($($codepoint:literal => $char:literal,)*) => {
         impl TryFrom<char> for EncodedChar {
             type Error = UnencodeableChar;
 
             #[forbid(unreachable_patterns)]
-            fn try_from(mut value: char) -> Result<Self, Self::Error> {
-                value.make_ascii_lowercase();
+            fn try_from(value: char) -> Result<Self, Self::Error> {
+                let value = downcase(value);
                 match value {
                     $(
                         $char => Ok(Self($codepoint)),
@@ -101,6 +295,7 @@ macro_rules! encoded_char_impls {
                     )*
                     EncodedChar(c) if (c as usize) == CHAR_SET_SIZE + 1 => '$',
                     NULL_CHAR => '&',
+                    BLOCK_CHAR => '#',
                     _ => '?',
                 }
             }
@@ -221,4 +416,153 @@ encoded_char_impls! {
     61 => 'ï',
     62 => 'ã',
     63 => 'ô',
-}
\ No newline at end of file
+}
+
+// Letters a-z, the common Latin letters with diacritics used across western and central European languages, and the Greek and Russian Cyrillic alphabets, with Greek's accented vowels. To make room, a few letters only one language needs (Portuguese ã, Czech ř, Turkish ğ, ı and ş, and Italian ì, ò and ù) are left out, as are - and ', use --fold-diacritics for those.
+#[cfg(feature = "charset-multilingual")]
+encoded_char_impls! {
+    0  => 'a',
+    1  => 'b',
+    2  => 'c',
+    3  => 'd',
+    4  => 'e',
+    5  => 'f',
+    6  => 'g',
+    7  => 'h',
+    8  => 'i',
+    9  => 'j',
+    10 => 'k',
+    11 => 'l',
+    12 => 'm',
+    13 => 'n',
+    14 => 'o',
+    15 => 'p',
+    16 => 'q',
+    17 => 'r',
+    18 => 's',
+    19 => 't',
+    20 => 'u',
+    21 => 'v',
+    22 => 'w',
+    23 => 'x',
+    24 => 'y',
+    25 => 'z',
+    26 => 'à',
+    27 => 'á',
+    28 => 'â',
+    29 => 'ä',
+    30 => 'å',
+    31 => 'æ',
+    32 => 'ç',
+    33 => 'è',
+    34 => 'é',
+    35 => 'ê',
+    36 => 'ë',
+    37 => 'í',
+    38 => 'î',
+    39 => 'ï',
+    40 => 'ñ',
+    41 => 'ó',
+    42 => 'ô',
+    43 => 'ö',
+    44 => 'ø',
+    45 => 'ú',
+    46 => 'û',
+    47 => 'ü',
+    48 => 'ß',
+    49 => 'œ',
+    50 => 'ą',
+    51 => 'ć',
+    52 => 'č',
+    53 => 'ę',
+    54 => 'ł',
+    55 => 'ń',
+    56 => 'ś',
+    57 => 'š',
+    58 => 'ź',
+    59 => 'ż',
+    60 => 'ž',
+    61 => 'α',
+    62 => 'β',
+    63 => 'γ',
+    64 => 'δ',
+    65 => 'ε',
+    66 => 'ζ',
+    67 => 'η',
+    68 => 'θ',
+    69 => 'ι',
+    70 => 'κ',
+    71 => 'λ',
+    72 => 'μ',
+    73 => 'ν',
+    74 => 'ξ',
+    75 => 'ο',
+    76 => 'π',
+    77 => 'ρ',
+    78 => 'σ',
+    79 => 'ς',
+    80 => 'τ',
+    81 => 'υ',
+    82 => 'φ',
+    83 => 'χ',
+    84 => 'ψ',
+    85 => 'ω',
+    86 => 'ά',
+    87 => 'έ',
+    88 => 'ή',
+    89 => 'ί',
+    90 => 'ό',
+    91 => 'ύ',
+    92 => 'ώ',
+    93 => 'ϊ',
+    94 => 'ϋ',
+    95 => 'а',
+    96 => 'б',
+    97 => 'в',
+    98 => 'г',
+    99 => 'д',
+    100=> 'е',
+    101=> 'ё',
+    102=> 'ж',
+    103=> 'з',
+    104=> 'и',
+    105=> 'й',
+    106=> 'к',
+    107=> 'л',
+    108=> 'м',
+    109=> 'н',
+    110=> 'о',
+    111=> 'п',
+    112=> 'р',
+    113=> 'с',
+    114=> 'т',
+    115=> 'у',
+    116=> 'ф',
+    117=> 'х',
+    118=> 'ц',
+    119=> 'ч',
+    120=> 'ш',
+    121=> 'щ',
+    122=> 'ъ',
+    123=> 'ы',
+    124=> 'ь',
+    125=> 'э',
+    126=> 'ю',
+    127=> 'я',
+}
+
+#[cfg(all(test, feature = "charset-auto"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn charset_file() {
+        assert_eq!(
+            parse_charset("a\nB\n\n=\né = e\nÈ=e\n"),
+            Ok((vec!['a', 'b', '='], vec![('é', 'e'), ('è', 'e')])),
+        );
+        assert_eq!(parse_charset("a\nab\n"), Err(AlphabetError::BadCharsetLine(2)));
+        assert_eq!(parse_charset("é = \n"), Err(AlphabetError::BadCharsetLine(1)));
+        assert_eq!(parse_charset("&\n"), Err(AlphabetError::BadCharsetLine(1)));
+    }
+}
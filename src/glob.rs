@@ -0,0 +1,120 @@
+use super::charset::CharSet;
+use crate::echar::{EncodedChar, BLOCK_CHAR, NULL_CHAR};
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+enum GlobPart {
+    // Exactly one letter from the set.
+    One(CharSet),
+    // Any run of letters, including none.
+    Any,
+}
+
+// A pattern a whole word has to match: letters match themselves, ? matches any one letter, * matches any run of letters, [abc] or [^abc] matches one letter in or not in a set, and [:V:] or [:C:] any vowel or consonant.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Glob(Vec<GlobPart>);
+
+impl std::str::FromStr for Glob {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut res = Vec::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            res.push(match c {
+                '*' => GlobPart::Any,
+                '?' => GlobPart::One(CharSet::full()),
+                '[' => GlobPart::One(parse_class(&mut chars)?),
+                c => {
+                    let mut set = CharSet::default();
+                    set.set(encode(c)?);
+                    GlobPart::One(set)
+                },
+            });
+        }
+        Ok(Glob(res))
+    }
+}
+
+impl Glob {
+    // Blocks only pad out words shorter than their row or column, so they're left out.
+    pub fn matches(&self, word: &[EncodedChar]) -> bool {
+        let word:Vec<EncodedChar> = word.iter().copied().filter(|&c| c != BLOCK_CHAR).collect();
+        // Where matching picks back up if what follows the last * doesn't match: just after that *, and one letter further into the word than last time.
+        let mut backtrack:Option<(usize, usize)> = None;
+        let (mut p, mut w) = (0, 0);
+        while w < word.len() {
+            match self.0.get(p) {
+                Some(GlobPart::Any) => {
+                    backtrack = Some((p + 1, w));
+                    p += 1;
+                    continue;
+                },
+                Some(GlobPart::One(set)) if set.has(word[w]) => {
+                    p += 1;
+                    w += 1;
+                    continue;
+                },
+                _ => (),
+            }
+            match backtrack {
+                Some((bp, bw)) => {
+                    backtrack = Some((bp, bw + 1));
+                    p = bp;
+                    w = bw + 1;
+                },
+                None => return false,
+            }
+        }
+        self.0[p..].iter().all(|&part| part == GlobPart::Any)
+    }
+}
+
+fn encode(c: char) -> Result<EncodedChar, String> {
+    match c.try_into() {
+        Ok(e) if e != NULL_CHAR => Ok(e),
+        _ => Err(format!("{:?} is not a valid char", c)),
+    }
+}
+
+// The vowels of each alphabet the built-in charsets cover, without diacritics. Letters with diacritics are vowels if their base letter is, so é, ά and ё are too. Y is a consonant, as is й, even though it's и with a breve.
+const VOWELS:&str = "aeiouæøœαεηιουωаеиоуыэюя";
+
+fn is_vowel(c: char) -> bool {
+    c != 'й' && VOWELS.contains(crate::wordlist::fold_diacritics(&c.to_string()).as_str())
+}
+
+// The Cyrillic hard and soft signs aren't sounds of their own, so they're neither.
+fn is_consonant(c: char) -> bool {
+    c.is_alphabetic() && !is_vowel(c) && c != 'ъ' && c != 'ь'
+}
+
+// Every letter in the alphabet that `pred` picks out.
+fn class_of(pred: impl Fn(char) -> bool) -> CharSet {
+    let mut res = CharSet::default();
+    for e in std::iter::successors(NULL_CHAR.inc(), |e| e.inc()) {
+        if pred(e.into()) {
+            res.set(e);
+        }
+    }
+    res
+}
+
+// Reads a set of letters like [abc], everything but a set like [^abc], or [:V:] or [:C:] for any vowel or consonant, from just after the [ up to and including the ]. Named classes are spelled so they can't be mistaken for letters.
+pub fn parse_class(chars: &mut std::str::Chars) -> Result<CharSet, String> {
+    for (name, pred) in [(":V:]", is_vowel as fn(char) -> bool), (":C:]", is_consonant)] {
+        if let Some(rest) = chars.as_str().strip_prefix(name) {
+            *chars = rest.chars();
+            return Ok(class_of(pred));
+        }
+    }
+    let mut set = CharSet::default();
+    let mut negated = false;
+    for (i, c) in chars.by_ref().enumerate() {
+        match c {
+            ']' => return Ok(if negated { CharSet::full().without(set) } else { set }),
+            '^' if i == 0 => negated = true,
+            c => set.set(encode(c)?),
+        }
+    }
+    Err("Unclosed [".to_string())
+}
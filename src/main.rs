@@ -8,42 +8,135 @@ macro_rules! highly_unsafe_garuntee {
     }
 }
 
+#[cfg(not(feature = "charset-auto"))]
 mod config;
 mod echar;
+#[cfg(not(feature = "charset-auto"))]
 mod charset;
-mod wordstuffs;
-#[cfg(feature = "serial")]
-mod serial_prefix_map;
+#[cfg(not(feature = "charset-auto"))]
+mod glob;
+mod sizes;
+mod wordlist;
 mod binary_searched_array_map;
 
 use std::io::{self, BufReader};
 use std::io::prelude::*;
 use std::fs::File;
-
-use progressing::{
-    Baring,
-    bernoulli::Bar as BernoulliBar,
-};
+use std::str::FromStr;
 
 use clap::{
     App,
     Arg
 };
 
-use config::*;
-use wordstuffs::*;
-use charset::*;
-use echar::*;
-#[cfg(feature = "serial")]
-use serial_prefix_map::*;
+use fnv::FnvHashSet;
+use wordlist::Wordlist;
 
 #[cfg(feature = "do-debug")]
 const DEBUG:bool = true;
 #[cfg(not(feature = "do-debug"))]
 const DEBUG:bool = false;
 
+// Everything from the command line that doesn't depend on the word rectangle size.
+#[derive(Default)]
+pub struct Options {
+    pub loud: bool,
+    pub ignore_empty_wordlist: bool,
+    pub fancy: bool,
+    pub show_progress: bool,
+    pub num_threads: usize,
+    pub count_rects: bool,
+    pub show_size: bool,
+    pub must_include: Vec<(String, Placement)>,
+    pub templates: Option<String>,
+    // Only set when the wordlist has scores, then every result is shown with its score.
+    pub score_mode: Option<wordlist::ScoreMode>,
+    // Only keep the best this many results, and show them once the search is done.
+    pub top: Option<usize>,
+    // No word can be used twice in one rect, across or down.
+    pub distinct: bool,
+    // Only search for squares that read the same across and down.
+    pub symmetric: bool,
+    // Only show one of each square and its transpose.
+    pub canonical: bool,
+    // Only show one of each rect and its flips, turns and transposes.
+    pub symmetry_group: bool,
+    // Only search for rects that are the same after each of these.
+    pub self_symmetric: Vec<SelfSymmetry>,
+    // Only search for squares whose diagonals spell words too.
+    pub diagonal: bool,
+    pub anti_diagonal: bool,
+    // Globs that every word in a row or column has to match, with the index of the row or column.
+    pub row_patterns: Vec<(usize, String)>,
+    pub col_patterns: Vec<(usize, String)>,
+    // Fill in the most constrained cell next, instead of going in a fixed order.
+    pub dynamic_order: bool,
+    // Check that each cell's row and column can still be finished as soon as it's filled in.
+    pub lookahead: bool,
+    // How many empty cells the main thread fills in before handing off to the worker threads. Picked for each template when not given.
+    pub split_depth: Option<usize>,
+}
+
+// A way of moving a rect's letters around that a result has to come out of unchanged.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum SelfSymmetry {
+    Rotate180,
+    // Squares only.
+    Rotate90,
+    MirrorLeftRight,
+    MirrorTopBottom,
+}
+
+impl FromStr for SelfSymmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rotate-180" => Ok(SelfSymmetry::Rotate180),
+            "rotate-90" => Ok(SelfSymmetry::Rotate90),
+            "mirror-left-right" => Ok(SelfSymmetry::MirrorLeftRight),
+            "mirror-top-bottom" => Ok(SelfSymmetry::MirrorTopBottom),
+            _ => Err(format!("{:?} is not one of rotate-180, rotate-90, mirror-left-right or mirror-top-bottom.", s)),
+        }
+    }
+}
+
+// Where a must-include word is allowed to go.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Placement {
+    Anywhere,
+    Across,
+    Down,
+    // Counting from 0, like --row and --col.
+    Row(usize),
+    Col(usize),
+}
+
+impl FromStr for Placement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let index = |n: &str| n.parse().map_err(|_| format!("{:?} is not a row or column number.", n));
+        match s {
+            "across" => Ok(Placement::Across),
+            "down" => Ok(Placement::Down),
+            _ if s.starts_with("row") => Ok(Placement::Row(index(&s[3..])?)),
+            _ if s.starts_with("col") => Ok(Placement::Col(index(&s[3..])?)),
+            _ => Err(format!("{:?} is not one of across, down, rowN or colN.", s)),
+        }
+    }
+}
+
+// Splits "sewer@row2" from --must-include into the word and where it goes. Without an @, it can go anywhere.
+fn parse_must_include(s: &str) -> Result<(String, Placement), String> {
+    match s.rsplit_once('@') {
+        Some((word, placement)) => Ok((word.to_string(), placement.parse()?)),
+        None => Ok((s.to_string(), Placement::Anywhere)),
+    }
+}
+
 fn main() -> io::Result<()> {
-    let args = App::new(format!("Fast Word Rectangle Finder o{}x{}", config::WORD_SQUARE_WIDTH, config::WORD_SQUARE_HEIGHT))
+    let args = App::new("Fast Word Rectangle Finder")
         .version(clap::crate_version!())
         .author(clap::crate_authors!())
         .about(clap::crate_description!())
@@ -60,9 +153,46 @@ fn main() -> io::Result<()> {
             .long("threads")
             .short("t")
         )
+        .arg(Arg::with_name("width")
+            .required_unless_one(&["sizes", "all-sizes"])
+            .conflicts_with_all(&["sizes", "all-sizes"])
+            .takes_value(true)
+            .validator(validate_dimension)
+            .help("Width of the word rectangles to search for, the length of the words in each row. Must be at least the height.")
+            .long("width")
+        )
+        .arg(Arg::with_name("height")
+            .required_unless_one(&["sizes", "all-sizes"])
+            .conflicts_with_all(&["sizes", "all-sizes"])
+            .takes_value(true)
+            .validator(validate_dimension)
+            .help("Height of the word rectangles to search for, the length of the words in each column.")
+            .long("height")
+        )
+        .arg(Arg::with_name("sizes")
+            .long("sizes")
+            .takes_value(true)
+            .conflicts_with("all-sizes")
+            .validator(|arg| parse_sizes(&arg).map(|_| ()))
+            .help("Search for several sizes in one go, reading the wordlist only once. A comma-separated list of sizes like 6x5 and/or ranges like 3x3..7x5, which means every width from 3 to 7 with every height from 3 to 5. Each result is prefixed with its size. Sizes that have no words are skipped.")
+        )
+        .arg(Arg::with_name("all-sizes")
+            .long("all-sizes")
+            .help("Search for every size from 2x2 to 15x15, same as --sizes 2x2..15x15")
+        )
         .arg(Arg::with_name("wordlist")
-            .required(true)
-            .help("the wordlist file path, a plain-text UTF-8 file with each word separated by a newline. Use - for stdin")
+            .required_unless_all(&["row-wordlist", "col-wordlist"])
+            .help("the wordlist file path, a plain-text UTF-8 file with each word separated by a newline. Use - for stdin. Each word can be followed by a tab and a score, like a frequency.")
+        )
+        .arg(Arg::with_name("row-wordlist")
+            .long("row-wordlist")
+            .takes_value(true)
+            .help("Take the words across (the rows) from this wordlist instead.")
+        )
+        .arg(Arg::with_name("col-wordlist")
+            .long("col-wordlist")
+            .takes_value(true)
+            .help("Take the words down (the columns) from this wordlist instead.")
         )
         .arg(Arg::with_name("ignore-empty-wordlist")
             .long("ignore-empty-wordlist")
@@ -88,7 +218,19 @@ fn main() -> io::Result<()> {
             .long("must-include")
             .short("m")
             .takes_value(true)
-            .help("Only search for word rectangles that include all of the given comma-separated words. These words are automatically added to the wordlist.")
+            .validator(|arg| arg.split(',').try_for_each(|s| parse_must_include(s).map(|_| ())))
+            .help("Only search for word rectangles that include all of the given comma-separated words. These words are automatically added to the wordlist. A word can be pinned to where it goes with @across, @down, @rowN or @colN, counting from 0, like sewer@row2,wet@col1.")
+        )
+        .arg(Arg::with_name("must-exclude")
+            .long("must-exclude")
+            .short("x")
+            .takes_value(true)
+            .help("Never use any of the given comma-separated words, as if they weren't in the wordlist.")
+        )
+        .arg(Arg::with_name("exclude-file")
+            .long("exclude-file")
+            .takes_value(true)
+            .help("Never use any of the words in this file, one per line like a wordlist. Anything after a tab is ignored, so a weighted wordlist works too.")
         )
         .arg(Arg::with_name("fancy-output")
             .long("fancy-output")
@@ -100,6 +242,110 @@ fn main() -> io::Result<()> {
             .short("a")
             .help("Filters words of all the same letter (like 'aaaaaa')")
         )
+        .arg(Arg::with_name("fold-diacritics")
+            .long("fold-diacritics")
+            .help("Reads letters with diacritics as the letter without them, so 'é' is read as 'e' and 'ñ' as 'n'. Results still show words spelled as they were in the wordlist.")
+        )
+        .arg(Arg::with_name("min-score")
+            .long("min-score")
+            .takes_value(true)
+            .validator(|arg| {
+                match arg.parse::<f64>() {
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(format!("Must provide a valid number. {:?}", e)),
+                }
+            })
+            .help("Drop words with a score below this. Words without a score count as 0.")
+        )
+        .arg(Arg::with_name("score")
+            .long("score")
+            .takes_value(true)
+            .possible_values(&["sum", "min", "product"])
+            .default_value("sum")
+            .help("How the scores of the words in a rectangle are combined. When the wordlist has scores, each result is shown with its score after a tab.")
+        )
+        .arg(Arg::with_name("top")
+            .long("top")
+            .takes_value(true)
+            .value_name("K")
+            .conflicts_with("count")
+            .validator(|arg| {
+                match arg.parse::<usize>() {
+                    Ok(0) => Err("Must be at least 1.".to_string()),
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(format!("Must provide a valid integer. {:?}", e)),
+                }
+            })
+            .help("Only outputs the K highest-scoring rects, best first, once the search is done. Needs a wordlist with scores. The search still goes in the usual order, but parts of it that can't beat the K best so far are skipped, so this can be much faster than sorting every result.")
+        )
+        .arg(Arg::with_name("distinct")
+            .long("distinct")
+            .help("Don't allow any word to be used twice in a rect, including as both a row and a column.")
+        )
+        .arg(Arg::with_name("symmetric")
+            .long("symmetric")
+            .conflicts_with("distinct")
+            .help("Only search for symmetric word squares, where each row is the same as the matching column. Only the cells on and above the diagonal are searched, so this is much faster than filtering. Non-square sizes are skipped.")
+        )
+        .arg(Arg::with_name("canonical")
+            .long("canonical")
+            .help("Only show one of each square and its transpose (its rows read as columns), whichever comes first reading row by row. Has no effect on non-square rects.")
+        )
+        .arg(Arg::with_name("symmetry-group")
+            .long("symmetry-group")
+            .help("Only show one of each rect and the rects it becomes when flipped top to bottom, flipped left to right, turned 180°, or for squares, transposed or turned 90°. Whichever comes first reading row by row is shown. Flips only make another result when the reversed words are in the wordlist too.")
+        )
+        .arg(Arg::with_name("self-symmetric")
+            .long("self-symmetric")
+            .takes_value(true)
+            .validator(|arg| arg.split(',').try_for_each(|s| s.parse::<SelfSymmetry>().map(|_| ())))
+            .help("A comma-separated list of rotate-180, rotate-90, mirror-left-right and mirror-top-bottom. Only search for rects that are the same after each of these, like the SATOR square turned 180°. Cells that have to match are filled in together, so only one of each is searched. rotate-90 only finds squares, and non-square sizes are skipped.")
+        )
+        .arg(Arg::with_name("diagonal")
+            .long("diagonal")
+            .help("Only search for squares whose diagonal from the top left to the bottom right is also a word from the (row) wordlist. Non-square sizes are skipped.")
+        )
+        .arg(Arg::with_name("anti-diagonal")
+            .long("anti-diagonal")
+            .help("Only search for squares whose diagonal from the top right to the bottom left, read downwards, is also a word from the (row) wordlist. Non-square sizes are skipped.")
+        )
+        .arg(Arg::with_name("row")
+            .long("row")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(|arg| parse_line_pattern(&arg).map(|_| ()))
+            .help("N=GLOB: Only use words matching GLOB in row N, counting from 0, like --row 2='*ing'. ? matches any one letter, * any run of letters, [abc] or [^abc] one letter in or not in a set, and [:V:] or [:C:] any vowel or consonant. Can be given more than once. Sizes without a row N are skipped.")
+        )
+        .arg(Arg::with_name("col")
+            .long("col")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(|arg| parse_line_pattern(&arg).map(|_| ()))
+            .help("N=GLOB: Like --row, but for column N.")
+        )
+        .arg(Arg::with_name("dynamic-order")
+            .long("dynamic-order")
+            .conflicts_with_all(&["symmetric", "self-symmetric", "diagonal", "anti-diagonal"])
+            .help("Instead of filling in cells in a fixed order, always fill in the open cell with the fewest letters that fit next. Costs a few more lookups per step, but can be much faster for templates with lots of fixed letters or blocks, where the fixed order branches on cells with nothing pinning them down.")
+        )
+        .arg(Arg::with_name("lookahead")
+            .long("lookahead")
+            .help("After filling in each cell, check that the rest of its row and column can still be finished with words that fit the letters crossing them so far, and back up straight away if not. Costs extra lookups at every step, but finds dead ends before the search gets to them. Unless --quiet is given, how many steps were cut off and how many lookups it took are shown at the end.")
+        )
+        .arg(Arg::with_name("split-depth")
+            .long("split-depth")
+            .takes_value(true)
+            .value_name("N")
+            .validator(|arg| {
+                match arg.parse::<usize>() {
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(format!("Must provide a valid integer. {:?}", e)),
+                }
+            })
+            .help("How many empty cells the main thread fills in before handing each partial rect off to the worker threads. 0 hands off each template whole. By default, it's the fewest that give every thread 16 jobs or more, going by a quick count of how many there are at each depth.")
+        )
         .arg(Arg::with_name("count")
             .long("count")
             .short("c")
@@ -108,601 +354,447 @@ fn main() -> io::Result<()> {
         .arg(Arg::with_name("templates")
             .long("templates")
             .takes_value(true)
-            .help(r#"A "pattern" the square must conform to. Much faster than filtering for a pattern after with grep or whatever. Compatible with --must-include. Use & to match any character, and separate each template with ! and each line within with |."#)
+            .help(r#"A "pattern" the square must conform to. Much faster than filtering for a pattern after with grep or whatever. Compatible with --must-include. Use & to match any character, # for a block (black square) that splits a row or column into shorter words, [abc] or [^abc] to match any character in or not in a set, [:V:] for any vowel and [:C:] for any consonant, and separate each template with ! and each line within with |."#)
+        )
+        .arg(Arg::with_name("charset")
+            .long("charset")
+            .takes_value(true)
+            .help("Use the characters listed in this file, one per line and in order, instead of the ones the wordlist uses. A line like `é = e` makes é read as e. Words with any other characters are dropped. Only for builds with the charset-auto feature, such as bin/fwrf-auto.")
         )
         .get_matches()
     ;
     
-    let loud = !args.is_present("quiet");
-    let ignore_empty_wordlist = args.is_present("ignore-empty-wordlist");
-    let ignore_unencodeable = args.is_present("ignore-unencodeable");
-    let fancy = args.is_present("fancy-output");
-    let show_progress = args.is_present("show-progress");
-    let num_threads:u32 = args.value_of("threads").unwrap().parse().unwrap();
-    let filter_aa = args.is_present("filter-aa");
-    let count_rects = args.is_present("count");
-    let arg_templates = args.value_of("templates");
-
-    let filename = args.value_of("wordlist").unwrap();
-    let f:BufReader<Box<dyn Read>> = if filename == "-" {
-        BufReader::new(Box::new(std::io::stdin()))
+    let mut search_sizes:Vec<(usize, usize)> = if args.is_present("all-sizes") {
+        parse_sizes(&format!("{0}x{0}..{1}x{1}", sizes::MIN_DIMENSION, sizes::MAX_DIMENSION)).unwrap()
+    } else if let Some(arg_sizes) = args.value_of("sizes") {
+        parse_sizes(arg_sizes).unwrap()
     } else {
-        BufReader::new(Box::new(File::open(filename)?))
-    };
-
-    let mut words:TheSet<EitherWord> = Default::default();
-
-    let mut lineno = 1;
-    for maybe_line in f.lines() {
-        if maybe_line.is_err() { eprintln!("Error on line {}", lineno); }
-        let line = maybe_line.unwrap();
-        lineno += 1;
-        match EitherWord::from_str_no_nulls(line.as_str()) {
-            Ok(w) => {
-                let s = w.as_slice();
-                let mut all_same = true;
-                for i in 1..s.len() {
-                    all_same = all_same && s[0] == s[i];
-                }
-                if !filter_aa || !all_same {
-                    words.insert(w);
-                }
-            },
-            Err(WordConversionError::WrongLength) => (),
-            Err(e) => {
-                if !ignore_unencodeable {
-                    panic!("Could not encode {:?} due to {:?}", &line, e);
-                }
-            }
+        let width:usize = args.value_of("width").unwrap().parse().unwrap();
+        let height:usize = args.value_of("height").unwrap().parse().unwrap();
+        if !sizes::is_supported(width, height) {
+            eprintln!("ERR: Width must be at least the height. Search for {}x{} instead and read the columns as rows.", height, width);
+            std::process::exit(1);
         }
-    }
-    
-    let must_include_strings:Vec<String> = args
+        vec![(width, height)]
+    };
+    let sweep = args.is_present("all-sizes") || args.is_present("sizes");
+    let line_patterns = |flag| -> Vec<(usize, String)> {
+        args.values_of(flag).into_iter().flatten().map(|s| parse_line_pattern(s).unwrap()).collect()
+    };
+    let (row_patterns, col_patterns) = (line_patterns("row"), line_patterns("col"));
+    let must_include:Vec<(String, Placement)> = args
         .value_of("must-include")
-        .map(|s| s
-            .split(',')
-            .map(str::to_string)
-            .collect()
-        )
+        .map(|s| s.split(',').map(|s| parse_must_include(s).unwrap()).collect())
         .unwrap_or_default();
-
-    // This is purposefully *not* a hashset, a word that appears twice in the must_include list must appear twice in any result word rectangles.
-    let mut must_include:Vec<EitherWord> = Vec::new();
-
-    for include_str in &must_include_strings {
-        match EitherWord::from_str_with_nulls(include_str.as_str()) {
-            Ok(word) => {
-                must_include.push(word);
-                words.insert(word);
-            },
-            Err(WordConversionError::WrongLength) => {
-                if ignore_empty_wordlist {
-                    std::process::exit(0);
-                } else {
-                    panic!("Must-use word {:?} length do not match dimensions.", include_str);
-                }
-            },
-            Err(e @ WordConversionError::UnencodeableChar(_,_)) => {
-                panic!("Error encoding must-use word {:?} due to {:?}", include_str, e);
-            },
-            Err(WordConversionError::NullChar) => unreachable!(),
+    // Sizes without every row and column that a pattern or a pinned word is given for are skipped.
+    let last_row = row_patterns.iter().map(|&(i, _)| i)
+        .chain(must_include.iter().filter_map(|&(_, p)| if let Placement::Row(i) = p { Some(i) } else { None }))
+        .max();
+    if let Some(i) = last_row {
+        search_sizes.retain(|&(_, height)| i < height);
+        if search_sizes.is_empty() {
+            eprintln!("ERR: Row {} is past the last row of every size searched.", i);
+            std::process::exit(1);
         }
     }
-
-    assert_eq!(must_include.len(), must_include_strings.len());
-
-    if show_progress && !must_include.is_empty() {
-        eprintln!("ERR: Cannot use both show-progress and must-use together.");
+    let last_col = col_patterns.iter().map(|&(i, _)| i)
+        .chain(must_include.iter().filter_map(|&(_, p)| if let Placement::Col(i) = p { Some(i) } else { None }))
+        .max();
+    if let Some(i) = last_col {
+        search_sizes.retain(|&(width, _)| i < width);
+        if search_sizes.is_empty() {
+            eprintln!("ERR: Column {} is past the last column of every size searched.", i);
+            std::process::exit(1);
+        }
+    }
+    let self_symmetric:Vec<SelfSymmetry> = args
+        .value_of("self-symmetric")
+        .map(|s| s.split(',').map(|v| v.parse().unwrap()).collect())
+        .unwrap_or_default();
+    // The other charsets are fixed when the binary is built.
+    #[cfg(not(feature = "charset-auto"))]
+    if args.is_present("charset") {
+        eprintln!("ERR: --charset only works in a build with the charset-auto feature, such as bin/fwrf-auto from make-bins.sh.");
         std::process::exit(1);
     }
-
-    let templates = if let Some(arg_templates) = arg_templates {
-        let mut res = vec![];
-        let thing:Vec<Vec<&str>> = arg_templates.split('!').map(|s| s.split('|').collect()).collect();
-        for (i, rect) in thing.into_iter().enumerate() {
-            if rect.len() != WORD_SQUARE_HEIGHT {
-                eprintln!("Error: Incorrect number of words in template {}", i);
+    // The serial prefix map follows each row and column from its first cell, which a symmetric search skips over, and has nothing for the diagonals or for single rows and columns. It's also only kept up to date going in a fixed order, one cell at a time, so it can't be looked ahead in.
+    #[cfg(feature = "serial")]
+    for flag in ["symmetric", "diagonal", "anti-diagonal", "row", "col", "dynamic-order", "lookahead"] {
+        if args.is_present(flag) {
+            eprintln!("ERR: --{} doesn't work with the serial prefix map.", flag);
+            std::process::exit(1);
+        }
+    }
+    let squares_only = [
+        (args.is_present("symmetric"), "--symmetric"),
+        (self_symmetric.contains(&SelfSymmetry::Rotate90), "--self-symmetric rotate-90"),
+        (args.is_present("diagonal"), "--diagonal"),
+        (args.is_present("anti-diagonal"), "--anti-diagonal"),
+    ];
+    for (present, flag) in squares_only {
+        if present {
+            search_sizes.retain(|&(width, height)| width == height);
+            if search_sizes.is_empty() {
+                eprintln!("ERR: {} only finds squares, so the width and height must be the same.", flag);
                 std::process::exit(1);
             }
-            let mut m = WordMatrix::default();
-            for (j, word) in rect.into_iter().enumerate() {
-                if word.len() != WORD_SQUARE_WIDTH {
-                    eprintln!("Error: Incorrect number of letters in word {} in template {}", j, i);
-                    std::process::exit(1);
-                }
-                let row:RowIndex = j.try_into().unwrap();
-                for (k, c) in word.chars().enumerate() {
-                    let col:ColIndex = k.try_into().unwrap();
-                    let e:EncodedChar = c.try_into().expect("Not a valid char in template");
-                    m[MatrixIndex{row, col}] = e;
-                }
-            }
-
-            res.push(m);
         }
-        res
-    } else { vec![Default::default()] };
-
-    let templates:Vec<WordMatrix> = make_templates(must_include.as_slice(), templates);
-
-    if DEBUG {
-        dbg!(&templates);
     }
+    // A --charset file is there to leave some characters out, so words using them are dropped as with -u.
+    let ignore_unencodeable = args.is_present("ignore-unencodeable") || args.is_present("charset");
+    let filter_aa = args.is_present("filter-aa");
+    let fold_diacritics = args.is_present("fold-diacritics");
+    let clean = |s: &str| {
+        let s = wordlist::normalize(s);
+        if fold_diacritics { wordlist::fold_diacritics(&s) } else { s }
+    };
 
-    if templates.is_empty() {
-        if ignore_empty_wordlist {
-            std::process::exit(0);
-        } else {
-            panic!("must-use words can not be fit together.");
-        }
+    let mut options = Options{
+        loud: !args.is_present("quiet"),
+        ignore_empty_wordlist: sweep || args.is_present("ignore-empty-wordlist"),
+        fancy: args.is_present("fancy-output"),
+        show_progress: args.is_present("show-progress"),
+        num_threads: args.value_of("threads").unwrap().parse().unwrap(),
+        count_rects: args.is_present("count"),
+        show_size: sweep,
+        must_include: must_include.into_iter().map(|(w, p)| (clean(&w), p)).collect(),
+        templates: args.value_of("templates").map(clean),
+        score_mode: None,
+        top: args.value_of("top").map(|s| s.parse().unwrap()),
+        distinct: args.is_present("distinct"),
+        symmetric: args.is_present("symmetric"),
+        canonical: args.is_present("canonical"),
+        symmetry_group: args.is_present("symmetry-group"),
+        self_symmetric,
+        diagonal: args.is_present("diagonal"),
+        anti_diagonal: args.is_present("anti-diagonal"),
+        row_patterns: row_patterns.into_iter().map(|(i, s)| (i, clean(&s))).collect(),
+        col_patterns: col_patterns.into_iter().map(|(i, s)| (i, clean(&s))).collect(),
+        dynamic_order: args.is_present("dynamic-order"),
+        lookahead: args.is_present("lookahead"),
+        split_depth: args.value_of("split-depth").map(|s| s.parse().unwrap()),
+    };
+    let has_blocks = options.templates.as_ref().map_or(false, |t| t.contains('#'));
+    #[cfg(feature = "serial")]
+    if has_blocks {
+        eprintln!("ERR: Blocks in templates don't work with the serial prefix map.");
+        std::process::exit(1);
     }
+    let min_score:Option<f64> = args.value_of("min-score").map(|s| s.parse().unwrap());
 
-    if loud {
-        eprintln!("Word rectangle order {}x{}", config::WORD_SQUARE_WIDTH, config::WORD_SQUARE_HEIGHT);
-        eprintln!("Start: creating index");
+    let mut excluded_words:Vec<String> = args
+        .value_of("must-exclude")
+        .map(|s| s.split(',').map(clean).collect())
+        .unwrap_or_default();
+    if let Some(exclude_filename) = args.value_of("exclude-file") {
+        for line in BufReader::new(File::open(exclude_filename)?).lines() {
+            let line = line?;
+            let word = line.split('\t').next().unwrap().trim();
+            if !word.is_empty() {
+                excluded_words.push(clean(word));
+            }
+        }
     }
 
-    if !ignore_empty_wordlist && words.is_empty() {
-        panic!("No words in wordlist!");
-    }
+    // Rows and columns each use their own wordlist if given one, and the main wordlist otherwise.
+    let row_filename = args.value_of("row-wordlist").or_else(|| args.value_of("wordlist")).unwrap();
+    let col_filename = args.value_of("col-wordlist").or_else(|| args.value_of("wordlist")).unwrap();
+    // Blocks in a template split rows and columns into shorter words, of any length down to 2.
+    let slot_lengths = if has_blocks { 2..sizes::MAX_DIMENSION } else { 0..0 };
+    let widths:Vec<usize> = search_sizes.iter().map(|&(width, _)| width).chain(slot_lengths.clone()).collect();
+    let heights:Vec<usize> = search_sizes.iter().map(|&(_, height)| height).chain(slot_lengths).collect();
 
-    if loud {
-        eprintln!("Starting.");
-    }
+    let (row_lines, col_lines) = if row_filename == col_filename {
+        let lengths:Vec<usize> = widths.iter().chain(&heights).copied().collect();
+        (read_lines(row_filename, &lengths, fold_diacritics, min_score)?, None)
+    } else {
+        (
+            read_lines(row_filename, &widths, fold_diacritics, min_score)?,
+            Some(read_lines(col_filename, &heights, fold_diacritics, min_score)?),
+        )
+    };
 
-    let compute_func = move |w2m_rx:std::sync::mpsc::Receiver<WordMatrix>| {
-        if fancy {
-            let mut minibuffer = String::new();
-            while let Ok(wm) = w2m_rx.recv() {
-                for row in RowIndex::all_values() {
-                    for col in ColIndex::all_values() {
-                        minibuffer.push(wm[MatrixIndex{row,col}].into());
-                    }
-                    minibuffer.push('\n');
-                }
-                println!("{}", minibuffer);
-                minibuffer.truncate(0);
-            }
-            Ok(())
+    // Must-include words and templates have to be encodeable too, even if the wordlist never uses some of their chars.
+    #[cfg(feature = "charset-auto")]
+    {
+        let res = if let Some(charset_filename) = args.value_of("charset") {
+            echar::set_alphabet_from_charset(std::fs::read_to_string(charset_filename)?.as_str())
         } else {
-            let mut minibuffer = String::new();
-            let mut writer = std::io::BufWriter::with_capacity(1024*1024, std::io::stdout());
-            while let Ok(wm) = w2m_rx.recv() {
-                for row in RowIndex::all_values() {
-                    for col in ColIndex::all_values() {
-                        minibuffer.push(wm[MatrixIndex{row,col}].into());
-                    }
-                    if row < RowIndex::MAX {
-                        minibuffer.push('|');
-                    }
-                }
-                minibuffer.push('\n');
-                writer.write_all(minibuffer.as_bytes())?;
-                minibuffer.truncate(0);
+            echar::set_alphabet(alphabet_chars(&options, row_lines.iter().chain(col_lines.iter().flatten())))
+        };
+        match res {
+            Ok(len) => if options.loud {
+                eprintln!("Alphabet of {} chars", len);
+            },
+            Err(e) => {
+                eprintln!("ERR: {}", e);
+                std::process::exit(1);
             }
-            writer.flush()?;
-            Ok(())
         }
-    };
+    }
 
-    let mut time = devtimer::DevTime::new_simple();
-    time.start();
+    // Multiplying in a negative score can make a rect better, so it's no use as an upper bound.
+    let has_negative_score = row_lines.iter().chain(col_lines.iter().flatten()).any(|l| l.score.map_or(false, |s| s < 0.0));
 
-    let count = outer_compute(
-        words,
-        templates.as_slice(),
-        num_threads as usize,
-        compute_func,
-        show_progress,
-        count_rects,
-    );
+    // Compared once encoded, so they match however the wordlist capitalizes them. Words that can't be encoded can't be in the wordlist either.
+    let excluded:FnvHashSet<Vec<echar::EncodedChar>> = excluded_words
+        .iter()
+        .filter_map(|w| wordlist::encode_str(w, false).ok())
+        .collect();
+    if let Some((word, _)) = options.must_include.iter().find(|(w, _)| {
+        wordlist::encode_str(w, false).map_or(false, |w| excluded.contains(&w))
+    }) {
+        eprintln!("ERR: {:?} is both a must-include and a must-exclude word.", word);
+        std::process::exit(1);
+    }
 
-    time.stop();
-    if loud {
-        eprintln!("Took {} secs", (time.time_in_micros().unwrap() as u64 as f64) / 1_000_000.0)
+    let wordlist = encode_lines(row_lines, &excluded, filter_aa, ignore_unencodeable);
+    let col_wordlist = col_lines.map(|lines| encode_lines(lines, &excluded, filter_aa, ignore_unencodeable));
+    if wordlist.is_weighted() || col_wordlist.as_ref().map_or(false, Wordlist::is_weighted) {
+        options.score_mode = Some(args.value_of("score").unwrap().parse().unwrap());
     }
-    if count_rects {
-        println!("{} rects found", count);
+    if options.top.is_some() {
+        if options.score_mode.is_none() {
+            eprintln!("ERR: --top needs a wordlist with scores.");
+            std::process::exit(1);
+        }
+        if options.score_mode == Some(wordlist::ScoreMode::Product) && has_negative_score {
+            eprintln!("ERR: --top with --score product needs every score to be at least 0.");
+            std::process::exit(1);
+        }
+    }
+
+    for (width, height) in search_sizes {
+        sizes::run(width, height, &options, &wordlist, col_wordlist.as_ref())?;
     }
 
     Ok(())
 }
 
-fn make_templates(
-    must_use: &[EitherWord],
-    from_templates: Vec<WordMatrix>,
-) -> Vec<WordMatrix> {
-    let (current_word, rest) = if let Some(v) = must_use.split_last() { v } else { return from_templates };
-    let mut to_templates = Vec::new();
-    each_dimension!(dim, {
-        if let Some(word) = dim::get_from_either(*current_word) {
-            for template in &from_templates {
-                for i in dim::Index::all_values() {
-                    if word.is_match(dim::index_matrix(*template, i)) {
-                        let mut new_matrix = *template;
-                        dim::set_matrix(&mut new_matrix, i, word);
-                        to_templates.push(new_matrix);
-                    }
-                }
-            }
-        }
-    });
-    make_templates(rest, to_templates)
+// Splits "2=*ing" from --row or --col into the index and the glob.
+fn parse_line_pattern(s: &str) -> Result<(usize, String), String> {
+    let (index, glob) = s.split_once('=').ok_or_else(|| format!("{:?} should be like 2='*ing'", s))?;
+    let index = index.parse().map_err(|_| format!("{:?} is not a row or column number", index))?;
+    Ok((index, glob.to_string()))
 }
 
-fn outer_compute(
-    wordlist: TheSet<EitherWord>,
-    templates: &[WordMatrix],
-    num_threads: usize,
-    output_func: impl 'static + Send + FnOnce(std::sync::mpsc::Receiver<WordMatrix>) -> Result<(), std::io::Error>,
-    show_progress: bool,
-    count_rects: bool,
-) -> u64 {
-    use std::sync::Arc;
-    #[cfg(feature = "serial")]
-    let prefix_map = serial_prefix_map::SerialPrefixMaps::new(&make_prefix_map(WordMatrix::default(), wordlist.iter().copied()).2);
-    #[cfg(feature = "serial")]
-    let prefix_map_arc = Arc::new(prefix_map);
-
-    let wordlist_arc = Arc::new(wordlist);
-    let (count_tx, count_rx) = crossbeam_channel::bounded::<u64>(2);
-    // "w2m" => worker threads to output thread
-    let (w2m_tx, w2m_rx) = std::sync::mpsc::sync_channel(4);
-    let output_thread = std::thread::spawn(move || output_func(w2m_rx));
-    let count_thread = std::thread::spawn(move || {
-        let mut count = 0;
-        while let Ok(msg) = count_rx.recv() {
-            count += msg;
-        }
-        count
-    });
-    for template in templates {
-        #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
-        let (_row_counts, _col_counts, prefix_map) = make_prefix_map(*template, wordlist_arc.iter().copied());
-
-        // "m2w" => main thread to worker threads
-        let (m2w_tx, m2w_rx) = crossbeam_channel::bounded::<WordMatrix>(2);
-        let (prog_tx, prog_rx) = crossbeam_channel::bounded::<()>(2);
-        let mut worker_handles = Vec::new();
-
-        #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
-        let prefix_map_arc = Arc::new(prefix_map);
-
-        
-        for _ in 0..num_threads {
-            let rxc = m2w_rx.clone();
-            let txc = w2m_tx.clone();
-            let countc = count_tx.clone();
-            let progc = prog_tx.clone();
-            let my_prefix_map = Arc::clone(&prefix_map_arc);
-            let my_wordlist = Arc::clone(&wordlist_arc);
-            worker_handles.push(
-                std::thread::spawn( move || {
-                    let mut thread_count = 0;
-                    while let Ok(msg) = rxc.recv() {
-                        compute(
-                            &my_prefix_map,
-                            msg,
-                            MatrixIndex{row: RowIndex::MAX, col: ColIndex::MAX},
-                            |a| {
-                                each_dimension!(dim, {
-                                    for i in dim::Index::all_values() {
-                                        let word = dim::index_matrix(a, i);
-                                        if !my_wordlist.contains(&word.into()) {
-                                            return
-                                        }
-                                    }
-                                });
-                                if count_rects {
-                                    thread_count += 1;
-                                } else {
-                                    txc.send(a).unwrap();
-                                }
-                            }
-                        );
-                        if show_progress {
-                            progc.send(()).unwrap();
-                        }
-                    }
-                    if count_rects {
-                        countc.send(thread_count).unwrap();
-                    }
-                })
-            );
-        }
+// Every char the search could have to encode: the wordlist's, and those of the must-include words, templates and patterns, less the ones that are part of a template or pattern's syntax.
+#[cfg(feature = "charset-auto")]
+fn alphabet_chars<'a>(options: &'a Options, lines: impl Iterator<Item = &'a WordLine>) -> Vec<char> {
+    let without_classes = |s: &str| s.replace("[:V:]", "").replace("[:C:]", "");
+    let template_chars = options.templates.iter()
+        .flat_map(|t| without_classes(t).chars().collect::<Vec<_>>())
+        .filter(|&c| !"!|#[]^".contains(c));
+    let pattern_chars = options.row_patterns.iter().chain(&options.col_patterns)
+        .flat_map(|(_, s)| without_classes(s).chars().collect::<Vec<_>>())
+        .filter(|&c| !"*?[]^".contains(c));
+    lines
+        .map(|l| &l.word)
+        .chain(options.must_include.iter().map(|(w, _)| w))
+        .flat_map(|l| l.chars())
+        .chain(template_chars)
+        .chain(pattern_chars)
+        .collect()
+}
 
-        let a = &*prefix_map_arc;
-        let mut mi = MatrixIndex::ZERO;
-        {
-            let mut nulls_so_far = 0;
-            while nulls_so_far < config::WORD_SQUARE_WIDTH-1 + 3 {
-                if template[mi] == NULL_CHAR { nulls_so_far += 1 }
-                mi = match mi.inc() {
-                    Some(v) => v,
-                    None => break,
-                }
-            }
-        }
-        if DEBUG { dbg!(mi); }
-
-        let mut count = 0;
-        let progress_bar_thread = if show_progress {
-            compute(a, *template, mi, |_| count += 1);
-            let mut progress_bar = BernoulliBar::with_goal(count).timed();
-            eprintln!("{}", progress_bar);
-            let mut last_progress_display = std::time::Instant::now();
-            Some(std::thread::spawn(move || {
-                while let Ok(_) = prog_rx.recv() {
-                    progress_bar.add(true);
-                    if last_progress_display.elapsed().as_secs() >= 1 {
-                        last_progress_display = std::time::Instant::now();
-                        eprintln!("{}", progress_bar);
-                    }
-                }
-            }))
-        } else { None };
+// A word as read from a wordlist, before it's encoded.
+struct WordLine {
+    word: String,
+    // The spelling from before --fold-diacritics, if that changed it.
+    original: Option<String>,
+    score: Option<f64>,
+}
 
-        let f = |ca| {
-            if DEBUG { dbg!(ca); }
-            m2w_tx.send(ca).unwrap();
-            if show_progress {
-            }
+// Reads the words of the given lengths, NFC-normalized. Each line is either just a word, or a word and its score separated by a tab.
+fn read_lines(filename: &str, lengths: &[usize], fold_diacritics: bool, min_score: Option<f64>) -> io::Result<Vec<WordLine>> {
+    let f:BufReader<Box<dyn Read>> = if filename == "-" {
+        BufReader::new(Box::new(std::io::stdin()))
+    } else {
+        BufReader::new(Box::new(File::open(filename)?))
+    };
+
+    let mut lines = Vec::new();
+    let mut lineno = 1;
+    for maybe_line in f.lines() {
+        if maybe_line.is_err() { eprintln!("Error on line {}", lineno); }
+        let line = maybe_line.unwrap();
+        let (word, score) = match line.split_once('\t') {
+            Some((word, score)) => match score.trim().parse::<f64>() {
+                Ok(score) => (word, Some(score)),
+                Err(e) => return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid score on line {} of {}. {:?}", lineno, filename, e),
+                )),
+            },
+            None => (line.as_str(), None),
         };
-        if DEBUG { dbg!(); }
-        //If there's only one worker thread, and we don't need to show progress, then there's no point "splitting up" the work
-        if worker_handles.len() == 1 && !show_progress{
-            m2w_tx.send(GenericMatrix([NULL_CHAR; WORD_SQUARE_SIZE])).unwrap();
-        } else {
-            compute(
-                a,
-                *template,
-                mi,
-                f,
-            );
+        lineno += 1;
+        let word = wordlist::normalize(word);
+        if !lengths.contains(&word.chars().count()) {
+            continue;
         }
-        if DEBUG { dbg!(); }
-
-        drop(m2w_tx);
-        drop(prog_tx);
-        for h in worker_handles {
-            h.join().unwrap();
+        if min_score.map_or(false, |min| score.unwrap_or(0.0) < min) {
+            continue;
         }
-        if let Some(t) = progress_bar_thread { t.join().unwrap() }
-        if DEBUG { dbg!(); }
+        if fold_diacritics {
+            let folded = wordlist::fold_diacritics(&word);
+            if folded != word {
+                lines.push(WordLine{word: folded, original: Some(word), score});
+                continue;
+            }
+        }
+        lines.push(WordLine{word, original: None, score});
     }
-    drop(count_tx);
-    let full_count:u64 = count_thread.join().unwrap();
-    if DEBUG { dbg!(); }
-    drop(w2m_tx);
-    output_thread.join().unwrap().unwrap();
-    if DEBUG { dbg!(); }
-    full_count
+    Ok(lines)
 }
 
-// It is assumed that this function does *not* need to be fast, and should be written in whatever way is reasonably fast and most correct and elegant.
-fn make_prefix_map<I>
-(
-    template: WordMatrix,
-    wordlist: I,
-) -> (usize, usize, WordPrefixMap)
-where
-    I: IntoIterator<Item = EitherWord>,
-{
-    let mut word_counts = [0usize; 2];
-    let mut res:WordPrefixMap = Default::default();
-    let mut word_templates = (vec![], vec![]);
-    each_dimension!(dim, {
-        let my_templates = dim::index_tuple_mut(&mut word_templates);
-        for i in dim::Index::all_values() {
-            let word = dim::index_matrix(template, i);
-            my_templates.push(word);
-        }
-        my_templates.sort();
-        my_templates.dedup();
-    });
-    #[cfg(feature = "square")]
-    {
-        for el in &word_templates.1 {
-            word_templates.0.push(*el);
-        }
-        word_templates.0.sort();
-        word_templates.0.dedup();
-    }
-    for w in wordlist {
-        each_unique_dimension!(dim, {
-            if let Some(w) = dim::get_from_either(w) {
-                word_counts[dim::DIMENSION_ID] += 1;
-                for c in &*w { assert_ne!(*c, NULL_CHAR); }
-                for &template in dim::index_tuple(&word_templates) {
-                    if template.is_match(w) {
-                        let p = w.prefixes(template);
-                        for (prefix,c) in p {
-                            dim::prefix_map_mut(&mut res).entry(prefix).or_default().set(c);
-                        }
+fn encode_lines(lines: Vec<WordLine>, excluded: &FnvHashSet<Vec<echar::EncodedChar>>, filter_aa: bool, ignore_unencodeable: bool) -> Wordlist {
+    let mut wordlist = Wordlist::default();
+    for WordLine{word, original, score} in lines {
+        match wordlist::encode_str(word.as_str(), false) {
+            Ok(w) => {
+                let all_same = w.iter().all(|&c| c == w[0]);
+                if (!filter_aa || !all_same) && !excluded.contains(&w) {
+                    wordlist.insert(w.as_slice(), score);
+                    if let Some(original) = original {
+                        wordlist.set_original(w.as_slice(), original.chars().map(echar::downcase).collect());
                     }
                 }
-            }
-        })
-    }
-    let row_counts = word_counts[wordstuffs::dim_row::DIMENSION_ID];
-    #[cfg(feature = "square")]
-    let col_counts = row_counts;
-    #[cfg(not(feature = "square"))]
-    let col_counts = word_counts[wordstuffs::dim_col::DIMENSION_ID];
-    (row_counts, col_counts, res)
-}
-
-fn compute<'a, F: FnMut(WordMatrix)>(
-    #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
-    prefix_map: &WordPrefixMap,
-    #[cfg(feature = "serial")]
-    prefix_map: &'a SerialPrefixMaps,
-    orig_matrix: WordMatrix,
-    target_idx: MatrixIndex,
-    mut on_result: F,
-) {
-    let mut at_idx = MatrixIndex::ZERO;
-    let mut charset_array:GenericMatrix<CharSet> = Default::default();
-    let mut is_nullish:GenericMatrix<bool> = GenericMatrix([true; config::WORD_SQUARE_SIZE]);
-    #[cfg(feature = "serial")]
-    let traversals_rows:GenericMatrix<Evil<'a>> = GenericMatrix([prefix_map.rows().top(); config::WORD_SQUARE_SIZE]);
-    #[cfg(feature = "serial")]
-    let traversals_cols:GenericMatrix<Evil<'a>> = GenericMatrix([prefix_map.cols().top(); config::WORD_SQUARE_SIZE]);
-    #[cfg(feature = "serial")]
-    let mut traversals = (traversals_rows, traversals_cols);
-    let mut matrix = orig_matrix;
-
-    for row in RowIndex::all_values() {
-        for col in ColIndex::all_values() {
-            let mi = MatrixIndex{row,col};
-            if orig_matrix[mi] != NULL_CHAR {
-                highly_unsafe_garuntee!(orig_matrix[mi].inner() < CHAR_SET_SIZE);
-                charset_array[mi].set(orig_matrix[mi]);
+            },
+            Err(e) => {
+                if !ignore_unencodeable {
+                    panic!("Could not encode {:?} due to {:?}", &word, e);
+                }
             }
         }
     }
+    wordlist
+}
 
-    loop {
-        if DEBUG {
-            dbg!(at_idx,matrix[at_idx]);
-        }
-        if is_nullish[at_idx] {
-            #[cfg(feature = "serial")]
-            let (row_set, col_set) = each_dimension!(dim, {
-                if DEBUG { dbg!(dim::DIMENSION_ID); }
-                let traversal = dim::index_tuple_mut(&mut traversals);
-                let cur_evil = dim::back(at_idx).map(|mi| {
-                    #[cfg(feature = "unchecked")]
-                    let c = unsafe { CharSetRanged::new_unchecked(matrix[mi].inner()) };
-                    #[cfg(not(feature = "unchecked"))]
-                    let c = matrix[mi].inner().try_into().unwrap();
-                    unsafe {
-                        traversal[mi].get_unchecked(c)
-                    }
-                }).unwrap_or(dim::prefix_map(&prefix_map).top());
-                traversal[at_idx] = cur_evil;
-                if DEBUG { dbg!(cur_evil.line()); }
-                cur_evil.charset()
-            });
-            if orig_matrix[at_idx] == NULL_CHAR {
-                #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
-                let (row_set, col_set) = each_dimension!(dim, {
-                    dim::prefix_map(prefix_map).get(&dim::get_word_intersecting_point(matrix, at_idx)).copied().unwrap_or_default()
-                });
-                charset_array[at_idx] = row_set.and(col_set);
-            }
-        }
+// Parses a comma-separated list of sizes (6x5) and ranges of sizes (3x3..7x5) into every matching supported size, ordered by width then height.
+fn parse_sizes(arg: &str) -> Result<Vec<(usize, usize)>, String> {
+    fn parse_size(s: &str) -> Result<(usize, usize), String> {
+        let (width, height) = s.split_once('x').ok_or_else(|| format!("{:?} is not a size like 6x5.", s))?;
+        let width = width.parse().map_err(|e| format!("Invalid width in {:?}. {:?}", s, e))?;
+        let height = height.parse().map_err(|e| format!("Invalid height in {:?}. {:?}", s, e))?;
+        Ok((width, height))
+    }
 
-        if orig_matrix[at_idx] == NULL_CHAR || !is_nullish[at_idx] {
-            match matrix[at_idx].inc() {
-                Some(e) => matrix[at_idx] = e,
-                None => {
-                    matrix[at_idx] = orig_matrix[at_idx];
-                    is_nullish[at_idx] = true;
-                    match at_idx.dec() {
-                        Some(i) => {
-                            at_idx = i;
-                        },
-                        None => return,
+    let mut res = Vec::new();
+    for part in arg.split(',') {
+        if let Some((start, end)) = part.split_once("..") {
+            let (start_width, start_height) = parse_size(start)?;
+            let (end_width, end_height) = parse_size(end)?;
+            let before = res.len();
+            for width in start_width..=end_width {
+                for height in start_height..=end_height {
+                    if sizes::is_supported(width, height) {
+                        res.push((width, height));
                     }
-                    continue;
                 }
             }
-        }
-
-        is_nullish[at_idx] = false;
-        highly_unsafe_garuntee!(matrix[at_idx].inner() < CHAR_SET_SIZE);
-        if charset_array[at_idx].has(matrix[at_idx]) {
-            let next = at_idx.inc();
-            if next == target_idx.inc() {
-                if DEBUG { dbg!(); }
-                (&mut on_result)(matrix);
-            } else if let Some(i) = next {
-                at_idx = i;
-            } else {
-                #[cfg(not(feature = "unchecked"))]
-                unreachable!();
-                #[cfg(feature = "unchecked")]
-                unsafe { std::hint::unreachable_unchecked() };
+            if res.len() == before {
+                return Err(format!("{:?} does not include any supported sizes.", part));
+            }
+        } else {
+            let (width, height) = parse_size(part)?;
+            if !sizes::is_supported(width, height) {
+                return Err(format!("{}x{} is not supported, the width must be at least the height and both must be between {} and {}.", width, height, sizes::MIN_DIMENSION, sizes::MAX_DIMENSION));
             }
+            res.push((width, height));
         }
     }
+    res.sort_unstable();
+    res.dedup();
+    Ok(res)
+}
+
+fn validate_dimension(arg: String) -> Result<(), String> {
+    match arg.parse::<usize>() {
+        Ok(n) if (sizes::MIN_DIMENSION..=sizes::MAX_DIMENSION).contains(&n) => Ok(()),
+        Ok(_) => Err(format!("Must be between {} and {}.", sizes::MIN_DIMENSION, sizes::MAX_DIMENSION)),
+        Err(e) => Err(format!("Must provide a valid integer. {:?}", e)),
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use std::sync::{Arc,Mutex};
     use super::*;
-    use config::{WORD_SQUARE_WIDTH,WORD_SQUARE_HEIGHT};
-
-    #[allow(dead_code)]
-    fn assert_results(
-        wordlist_str: &[&str],
-        must_use_str: &[&str],
-        expected_results_str: &[&[&str]],
-    ) {
-        let mut expected_results:Vec<_> = expected_results_str.iter().map(|str_a| {
-            let mut m = WordMatrix::default();
-            assert_eq!(str_a.len(), WORD_SQUARE_HEIGHT);
-            for rowi in RowIndex::all_values() {
-                let row_chars:Vec<_> = str_a[rowi.into():usize].chars().collect();
-                assert_eq!(row_chars.len(), WORD_SQUARE_WIDTH);
-                for coli in ColIndex::all_values() {
-                    let mi = MatrixIndex{row: rowi, col: coli};
-                    m[mi] = row_chars[coli.into():usize].try_into().unwrap();
-                }
-            }
-            m
-        }).collect();
-        if DEBUG { dbg!(); }
+    use sizes::*;
+    use wordlist::ScoreMode;
 
-        expected_results.sort();
+    // Enough three letter words to make a handful of squares, with or without letters given.
+    const THREE_LETTER_WORDS:&[&str] = &["bat", "cot", "act", "tab", "oat", "tea", "aha", "cat", "ate", "eat", "tae", "tot", "bee", "abe", "tee", "ace", "cab"];
+    // Fills the slots of a 3x3 with blocks in opposite corners, `&&#|&&&|#&&`.
+    const CROSSWORD_WORDS:&[&str] = &["at", "to", "ten", "ant", "eta", "ne", "an", "tea"];
+    const CROSSWORD_RESULTS:&[&[&str]] = &[
+        &["at#", "tea", "#an"],
+        &["at#", "tea", "#at"],
+        &["at#", "ten", "#ne"],
+        &["ne#", "eta", "#an"],
+        &["ne#", "eta", "#at"],
+    ];
 
-        let wordlist:TheSet<EitherWord> = wordlist_str.iter().map(|&s| EitherWord::from_str_no_nulls(s).unwrap()).collect();
-        let must_use:Vec<EitherWord> = must_use_str.iter().map(|&s| EitherWord::from_str_with_nulls(s).unwrap()).collect();
-        let templates:Vec<WordMatrix> = make_templates(must_use.as_slice(),vec![Default::default()]);
-        let results_mutex = Arc::new(Mutex::new(Vec::new()));
-        if DEBUG { dbg!(); }
+    fn wordlist(words: &[&str]) -> Wordlist {
+        scored_wordlist(&words.iter().map(|&w| (w, None)).collect::<Vec<_>>())
+    }
 
-        let their_results_mutex = Arc::clone(&results_mutex);
-        outer_compute(
-            wordlist,
-            templates.as_slice(),
-            1,
-            move |rx| {
-                let mut results_lock = their_results_mutex.lock().unwrap();
-                if DEBUG { dbg!(); }
-                while let Ok(ws) = rx.recv() { results_lock.push(ws); }
-                drop(results_lock);
-                drop(their_results_mutex);
-                if DEBUG { dbg!(); }
-                Ok(())
-            },
-            false,
-            false,
-        );
-        if DEBUG { dbg!(); }
+    fn scored_wordlist(words: &[(&str, Option<f64>)]) -> Wordlist {
+        echar::test_alphabet();
+        let mut res = Wordlist::default();
+        for &(w, score) in words {
+            res.insert(&wordlist::encode_str(w, false).unwrap(), score);
+        }
+        res
+    }
 
-        let mut lock = results_mutex.lock().unwrap();
-        let mut results = Vec::new();
-        if DEBUG { dbg!(); }
-        std::mem::swap(&mut results, &mut lock);
-        drop(lock);
-        drop(results_mutex);
+    // A search with nothing but the wordlist, on one thread.
+    fn plain() -> Options {
+        Options{num_threads: 1, ..Default::default()}
+    }
 
-        results.sort();
-        if DEBUG { dbg!(); }
+    fn must_include(words: &[&str]) -> Options {
+        Options{must_include: words.iter().map(|s| parse_must_include(s).unwrap()).collect(), ..plain()}
+    }
 
-        assert_eq!(results, expected_results);
+    fn template(template: &str) -> Options {
+        Options{templates: Some(template.to_string()), ..plain()}
+    }
+
+    #[test]
+    fn size_ranges() {
+        assert_eq!(parse_sizes("6x5"), Ok(vec![(6,5)]));
+        assert_eq!(parse_sizes("5x5,3x2,5x5"), Ok(vec![(3,2),(5,5)]));
+        assert_eq!(parse_sizes("3x3..5x4"), Ok(vec![(3,3),(4,3),(4,4),(5,3),(5,4)]));
+        assert_eq!(parse_sizes("2x2..15x15").unwrap().len(), 105);
+        assert!(parse_sizes("4x6").is_err());
+        assert!(parse_sizes("3x4..3x5").is_err());
+        assert!(parse_sizes("5").is_err());
+    }
+
+    #[test]
+    /// --width and --height pick the size at runtime, and sizes without a module are turned down rather than panicking.
+    fn size_dispatch() {
+        assert!(is_supported(3, 3));
+        assert!(is_supported(15, 2));
+        assert!(!is_supported(4, 6));
+        assert!(!is_supported(16, 2));
+        let counting = Options{count_rects: true, ..plain()};
+        let words = wordlist(THREE_LETTER_WORDS);
+        assert!(run(3, 3, &counting, &words, None).is_ok());
+        for (width, height) in [(4, 6), (16, 2)] {
+            let err = run(width, height, &counting, &words, None).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        }
     }
 
-    #[cfg(all(feature = "width-5", feature = "height-5"))]
     #[test]
     fn sator_square() {
         if DEBUG { dbg!(); }
-        assert_results(
-            &["sator","arepo","opera","rotas","tenet"],
-            &[],
+        w5x5::solver::test::assert_results(
+            &plain(),
+            &wordlist(&["sator","arepo","opera","rotas","tenet"]),
+            None,
             &[
                 &[
                     "sator",
@@ -722,12 +814,12 @@ mod test {
         );
     }
 
-    #[cfg(all(feature = "width-5", feature = "height-5"))]
     #[test]
     fn aaaaa() {
-        assert_results(
-            &["aaaaa"], 
-            &[],
+        w5x5::solver::test::assert_results(
+            &plain(),
+            &wordlist(&["aaaaa"]),
+            None,
             &[
                 &[
                     "aaaaa",
@@ -740,11 +832,11 @@ mod test {
         );
     }
 
-    #[cfg(all(feature = "width-6", feature = "height-4"))]
     #[test]
     fn fwrf() {
-        assert_results(
-            &[
+        w6x4::solver::test::assert_results(
+            &plain(),
+            &wordlist(&[
                 "fresco",
                 "worker",
                 "raging",
@@ -755,8 +847,8 @@ mod test {
                 "skis",
                 "cent",
                 "orgy",
-            ],
-            &[],
+            ]),
+            None,
             &[
                 &[
                     "fresco",
@@ -768,29 +860,502 @@ mod test {
         );
     }
 
-    #[cfg(all(feature = "width-4", feature = "height-2"))]
+    #[test]
+    /// Swapping the row and column wordlists should give the transposed result, and not the original one as it would with a shared wordlist.
+    fn separate_col_wordlist() {
+        w2x2::solver::test::assert_results(
+            &plain(),
+            &wordlist(&["ab", "cd"]),
+            Some(&wordlist(&["ac", "bd"])),
+            &[&["ab", "cd"]],
+        );
+        w2x2::solver::test::assert_results(
+            &plain(),
+            &wordlist(&["ac", "bd"]),
+            Some(&wordlist(&["ab", "cd"])),
+            &[&["ac", "bd"]],
+        );
+    }
+
+    #[test]
+    /// Every 2x2 of a and b is a result, aa|aa scores 4*4 and aa|ab scores 4+3+4+3, and the rest score 13 or less.
+    fn top_by_score() {
+        let wordlist = scored_wordlist(&[("aa", Some(4.0)), ("ab", Some(3.0)), ("ba", Some(2.0)), ("bb", Some(1.0))]);
+        w2x2::solver::test::assert_results(
+            &Options{score_mode: Some(ScoreMode::Sum), top: Some(2), ..plain()},
+            &wordlist,
+            None,
+            &[&["aa", "aa"], &["aa", "ab"]],
+        );
+        // The worst word in every other rect is at most ab.
+        w2x2::solver::test::assert_results(
+            &Options{score_mode: Some(ScoreMode::Min), top: Some(1), ..plain()},
+            &wordlist,
+            None,
+            &[&["aa", "aa"]],
+        );
+        // With blocks, each slot is scored as its own word. at#|ten|#ne scores 2*(1+5+4) and ne#|eta|#an 2*(4+1+2), the other three less.
+        let wordlist = scored_wordlist(&[
+            ("at", Some(1.0)), ("to", Some(0.0)), ("ten", Some(5.0)), ("ant", Some(0.0)),
+            ("eta", Some(1.0)), ("ne", Some(4.0)), ("an", Some(2.0)), ("tea", Some(1.0)),
+        ]);
+        w3x3::solver::test::assert_results(
+            &Options{score_mode: Some(ScoreMode::Sum), top: Some(2), ..template("&&#|&&&|#&&")},
+            &wordlist,
+            None,
+            &[&["at#", "ten", "#ne"], &["ne#", "eta", "#an"]],
+        );
+    }
+
+    #[test]
+    /// All but two of these use some word twice, such as ab|bd which has ab and bd both across and down.
+    fn distinct_words() {
+        let wordlist = wordlist(&["ab", "cd", "ac", "bd", "aa"]);
+        w2x2::solver::test::assert_results(
+            &plain(),
+            &wordlist,
+            None,
+            &[&["aa", "aa"], &["aa", "ab"], &["aa", "ac"], &["ab", "bd"], &["ab", "cd"], &["ac", "bd"], &["ac", "cd"]],
+        );
+        w2x2::solver::test::assert_results(
+            &Options{distinct: true, ..plain()},
+            &wordlist,
+            None,
+            &[&["ab", "cd"], &["ac", "bd"]],
+        );
+        // With blocks each slot is a word, so ab#|cde|#ab is out for its two ab across, and so are the rest but these four.
+        w3x3::solver::test::assert_results(
+            &Options{distinct: true, ..template("&&#|&&&|#&&")},
+            &self::wordlist(&["ab", "ac", "cde", "bdf", "fg", "eg", "ef", "bda", "ag", "ca", "cab", "eb"]),
+            None,
+            &[&["ab#", "cde", "#fg"], &["ab#", "cde", "#ag"], &["ac#", "bdf", "#eg"], &["ac#", "bda", "#eg"]],
+        );
+        // The letters the main thread fills in before handing off, up to the whole square, are checked too.
+        for dynamic_order in [false, true] {
+            for split_depth in [Some(1), Some(4), None] {
+                w2x2::solver::test::assert_results(
+                    &Options{distinct: true, dynamic_order, split_depth, num_threads: 2, ..plain()},
+                    &wordlist,
+                    None,
+                    &[&["ab", "cd"], &["ac", "bd"]],
+                );
+            }
+        }
+    }
+
+    #[test]
+    /// Same wordlist as distinct_words, the other five results are the same read across or down.
+    fn symmetric_squares() {
+        w2x2::solver::test::assert_results(
+            &Options{symmetric: true, ..plain()},
+            &wordlist(&["ab", "cd", "ac", "bd", "aa"]),
+            None,
+            &[&["aa", "aa"], &["aa", "ab"], &["aa", "ac"], &["ab", "bd"], &["ac", "cd"]],
+        );
+        w5x5::solver::test::assert_results(
+            &Options{symmetric: true, ..plain()},
+            &wordlist(&["sator","arepo","opera","rotas","tenet"]),
+            None,
+            &[
+                &["sator", "arepo", "tenet", "opera", "rotas"],
+                &["rotas", "opera", "tenet", "arepo", "sator"],
+            ],
+        );
+    }
+
+    #[test]
+    /// Same wordlist as distinct_words, ab|cd and ac|bd are each other's transpose so only the first is kept.
+    fn canonical_squares() {
+        w2x2::solver::test::assert_results(
+            &Options{canonical: true, ..plain()},
+            &wordlist(&["ab", "cd", "ac", "bd", "aa"]),
+            None,
+            &[&["aa", "aa"], &["aa", "ab"], &["aa", "ac"], &["ab", "bd"], &["ab", "cd"], &["ac", "cd"]],
+        );
+    }
+
+    #[test]
+    /// The two SATOR squares are the same turned 180°. ac|bd is ab|cd transposed, but none of these flipped are results since ba, ca, db and dc aren't words.
+    fn symmetry_group() {
+        w5x5::solver::test::assert_results(
+            &Options{symmetry_group: true, ..plain()},
+            &wordlist(&["sator","arepo","opera","rotas","tenet"]),
+            None,
+            &[&["rotas", "opera", "tenet", "arepo", "sator"]],
+        );
+        w2x2::solver::test::assert_results(
+            &Options{symmetry_group: true, ..plain()},
+            &wordlist(&["ab", "cd", "ac", "bd"]),
+            None,
+            &[&["ab", "bd"], &["ab", "cd"], &["ac", "cd"]],
+        );
+    }
+
+    #[test]
+    /// Both SATOR squares read the same turned 180°. Mirrored, ab|ba is ba|ab, so it only counts when both are the same.
+    fn self_symmetric() {
+        w5x5::solver::test::assert_results(
+            &Options{self_symmetric: vec![SelfSymmetry::Rotate180], ..plain()},
+            &wordlist(&["sator","arepo","opera","rotas","tenet"]),
+            None,
+            &[&["rotas", "opera", "tenet", "arepo", "sator"], &["sator", "arepo", "tenet", "opera", "rotas"]],
+        );
+        w2x2::solver::test::assert_results(
+            &Options{self_symmetric: vec![SelfSymmetry::Rotate180], ..plain()},
+            &wordlist(&["ab", "ba", "ac", "bd", "cd"]),
+            None,
+            &[&["ab", "ba"], &["ba", "ab"]],
+        );
+        w2x2::solver::test::assert_results(
+            &Options{self_symmetric: vec![SelfSymmetry::MirrorLeftRight], ..plain()},
+            &wordlist(&["ab", "ba", "aa"]),
+            None,
+            &[&["aa", "aa"]],
+        );
+    }
+
+    #[test]
+    /// bb isn't a word, so ba|ab is out when the diagonal has to be one, and ab|ba when the anti-diagonal does.
+    fn diagonal_words() {
+        let wordlist = wordlist(&["ab", "ba", "aa"]);
+        let all:&[&[&str]] = &[&["aa", "aa"], &["aa", "ab"], &["aa", "ba"], &["ab", "aa"], &["ab", "ba"], &["ba", "aa"], &["ba", "ab"]];
+        w2x2::solver::test::assert_results(
+            &Options{diagonal: true, ..plain()},
+            &wordlist,
+            None,
+            &all.iter().copied().filter(|&r| r != ["ba", "ab"]).collect::<Vec<_>>(),
+        );
+        w2x2::solver::test::assert_results(
+            &Options{anti_diagonal: true, ..plain()},
+            &wordlist,
+            None,
+            &all.iter().copied().filter(|&r| r != ["ab", "ba"]).collect::<Vec<_>>(),
+        );
+        w2x2::solver::test::assert_results(
+            &Options{diagonal: true, anti_diagonal: true, ..plain()},
+            &wordlist,
+            None,
+            &all.iter().copied().filter(|&r| r != ["ba", "ab"] && r != ["ab", "ba"]).collect::<Vec<_>>(),
+        );
+    }
+
+    #[test]
+    /// Of the squares the wordlist makes, only these two have a middle row not starting with a and an a in the last column.
+    fn line_patterns() {
+        w3x3::solver::test::assert_results(
+            &Options{
+                row_patterns: vec![(1, "[^a]*".to_string())],
+                col_patterns: vec![(2, "*a*".to_string())],
+                ..plain()
+            },
+            &wordlist(THREE_LETTER_WORDS),
+            None,
+            &[&["tea", "eat", "ate"], &["ate", "tea", "eat"]],
+        );
+    }
+
+    #[test]
+    /// The blocks leave two 2-letter slots and a 3-letter one each way, and the corners next to the blocks are single cells in one direction.
+    fn crossword_blocks() {
+        w3x3::solver::test::assert_results(
+            &template("&&#|&&&|#&&"),
+            &wordlist(CROSSWORD_WORDS),
+            None,
+            CROSSWORD_RESULTS,
+        );
+    }
+
+    #[test]
+    /// Each cell's class rules out some of the squares the wordlist makes without a template. Capital letters are still just letters.
+    fn cell_classes() {
+        w3x3::solver::test::assert_results(
+            &template("[:C:][:V:]&|&[bc]&|[ta]&[^e]"),
+            &wordlist(THREE_LETTER_WORDS),
+            None,
+            &[
+                &["bat", "abe", "tea"],
+                &["bat", "ace", "tea"],
+                &["cat", "abe", "tea"],
+                &["cat", "ace", "tea"],
+            ],
+        );
+        w3x3::solver::test::assert_results(
+            &template("C&&|&T&|&&E"),
+            &wordlist(THREE_LETTER_WORDS),
+            None,
+            &[
+                &["cab", "ate", "bee"],
+                &["cab", "ate", "tee"],
+                &["cat", "ate", "bee"],
+                &["cat", "ate", "tee"],
+            ],
+        );
+    }
+
+    #[test]
+    /// Going by the fewest letters left gives the same squares as going row by row, with or without blocks.
+    fn dynamic_order() {
+        w3x3::solver::test::assert_results(
+            &Options{dynamic_order: true, ..template("c&&|&t&|&&e")},
+            &wordlist(THREE_LETTER_WORDS),
+            None,
+            &[
+                &["cab", "ate", "bee"],
+                &["cab", "ate", "tee"],
+                &["cat", "ate", "bee"],
+                &["cat", "ate", "tee"],
+            ],
+        );
+        w3x3::solver::test::assert_results(
+            &Options{dynamic_order: true, ..template("&&#|&&&|#&&")},
+            &wordlist(CROSSWORD_WORDS),
+            None,
+            CROSSWORD_RESULTS,
+        );
+    }
+
+    #[test]
+    /// Looking ahead only cuts off squares that couldn't be finished, in either order.
+    fn lookahead() {
+        for dynamic_order in [false, true] {
+            let mut cut = 0;
+            let strategy = w3x3::solver::test::assert_results(
+                &Options{dynamic_order, lookahead: true, ..template("c&&|&t&|&&e")},
+                &wordlist(THREE_LETTER_WORDS),
+                None,
+                &[
+                    &["cab", "ate", "bee"],
+                    &["cab", "ate", "tee"],
+                    &["cat", "ate", "bee"],
+                    &["cat", "ate", "tee"],
+                ],
+            );
+            cut += strategy.lookahead.unwrap().cut.load(std::sync::atomic::Ordering::Relaxed);
+            let strategy = w3x3::solver::test::assert_results(
+                &Options{dynamic_order, lookahead: true, ..template("&&#|&&&|#&&")},
+                &wordlist(CROSSWORD_WORDS),
+                None,
+                CROSSWORD_RESULTS,
+            );
+            cut += strategy.lookahead.unwrap().cut.load(std::sync::atomic::Ordering::Relaxed);
+            // The b in the corner limits the cells before it in its row and column, which aren't filled up to yet.
+            let strategy = w3x3::solver::test::assert_results(
+                &Options{dynamic_order, lookahead: true, ..template("&&&|&&&|&&b")},
+                &wordlist(THREE_LETTER_WORDS),
+                None,
+                &[
+                    &["bat", "aha", "tab"],
+                    &["cat", "aha", "tab"],
+                    &["eat", "aha", "tab"],
+                    &["oat", "aha", "tab"],
+                ],
+            );
+            cut += strategy.lookahead.unwrap().cut.load(std::sync::atomic::Ordering::Relaxed);
+            assert!(cut > 0);
+        }
+    }
+
+    #[test]
+    #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+    /// Below a full top row, the column down from its last cell has to spell cde, but no row has a d in its last cell. Rows 1 and 2 aren't filled up to that column yet, so only their line masks know that.
+    fn lookahead_line_masks() {
+        let (with_masks, without_masks) = w3x3::solver::test::lookahead_allows(
+            &Options{lookahead: true, ..plain()},
+            &wordlist(&["abc", "cde"]),
+            &["abc", "&&&", "&&&"],
+            (0, 2),
+        );
+        assert!(!with_masks);
+        assert!(without_masks);
+    }
+
+    #[test]
+    #[cfg(feature = "charset-multilingual")]
+    /// Greek, Cyrillic and accented Latin words come back out as they went in, capitals are downcased, and the final sigma stays its own letter.
+    fn multilingual() {
+        let encode = |w: &str| wordlist::encode_str(w, false).unwrap();
+        for w in ["λόγος", "αϋπνία", "жизнь", "ёж", "żółć", "façade", "straße", "ñandú"] {
+            let decoded:String = encode(w).into_iter().map(char::from).collect();
+            assert_eq!(decoded, w);
+        }
+        assert_eq!(encode("ΛΌΓΟΣ"), encode("λόγοσ"));
+        assert_ne!(encode("ΛΌΓΟΣ"), encode("λόγος"));
+        assert_ne!(encode("ς"), encode("σ"));
+        assert_ne!(encode("ό"), encode("ο"));
+        assert_eq!(encode("ЁЖ"), encode("ёж"));
+        assert_ne!(encode("ёж"), encode("еж"));
+        w2x2::solver::test::assert_results(
+            &plain(),
+            &wordlist(&["το", "ος", "он", "на"]),
+            None,
+            &[
+                &["το", "ος"],
+                &["он", "на"],
+            ],
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "charset-multilingual")]
+    /// The vowel and consonant classes know Greek and Cyrillic too, and й is a consonant even though it's и with a breve.
+    fn multilingual_classes() {
+        let words = wordlist(&["ёж", "жё", "ай", "йа", "ωμ", "μω"]);
+        w2x2::solver::test::assert_results(
+            &template("[:V:]&|&&"),
+            &words,
+            None,
+            &[
+                &["ёж", "жё"],
+                &["ай", "йа"],
+                &["ωμ", "μω"],
+            ],
+        );
+        w2x2::solver::test::assert_results(
+            &template("[:C:]&|&&"),
+            &words,
+            None,
+            &[
+                &["жё", "ёж"],
+                &["йа", "ай"],
+                &["μω", "ωμ"],
+            ],
+        );
+    }
+
+    #[test]
+    /// Worker threads splitting their searches off for each other still find each square once, in either order.
+    fn work_stealing() {
+        for dynamic_order in [false, true] {
+            w3x3::solver::test::assert_results(
+                &Options{dynamic_order, num_threads: 4, ..template("&&&|&h&|&&&")},
+                &wordlist(THREE_LETTER_WORDS),
+                None,
+                &[
+                    &["bat", "aha", "tab"],
+                    &["bat", "aha", "tae"],
+                    &["cab", "aha", "bat"],
+                    &["cat", "aha", "tab"],
+                    &["cat", "aha", "tae"],
+                    &["eat", "aha", "tab"],
+                    &["eat", "aha", "tae"],
+                    &["oat", "aha", "tab"],
+                    &["oat", "aha", "tae"],
+                    &["tab", "aha", "bat"],
+                    &["tab", "aha", "eat"],
+                    &["tae", "aha", "bat"],
+                    &["tae", "aha", "eat"],
+                ],
+            );
+            w3x3::solver::test::assert_results(
+                &Options{dynamic_order, num_threads: 4, ..template("&&#|&&&|#&&")},
+                &wordlist(CROSSWORD_WORDS),
+                None,
+                CROSSWORD_RESULTS,
+            );
+        }
+    }
+
+    #[test]
+    /// However deep the main thread goes before handing off, including not at all or past the last empty cell, every square is found once, in either order. Each of them reads the same across and down, so with --distinct none are left, even those handed off already filled in.
+    fn split_depth() {
+        for dynamic_order in [false, true] {
+            for split_depth in [Some(0), Some(1), Some(4), Some(20), None] {
+                w3x3::solver::test::assert_results(
+                    &Options{split_depth, dynamic_order, num_threads: 2, ..template("&&&|&&&|&&b")},
+                    &wordlist(THREE_LETTER_WORDS),
+                    None,
+                    &[
+                        &["bat", "aha", "tab"],
+                        &["cat", "aha", "tab"],
+                        &["eat", "aha", "tab"],
+                        &["oat", "aha", "tab"],
+                    ],
+                );
+                w3x3::solver::test::assert_results(
+                    &Options{split_depth, dynamic_order, distinct: true, num_threads: 2, ..template("&&&|&&&|&&b")},
+                    &wordlist(THREE_LETTER_WORDS),
+                    None,
+                    &[],
+                );
+            }
+        }
+    }
+
+    #[test]
+    /// Excluded words are dropped however the wordlist capitalizes them.
+    fn exclude_words() {
+        echar::test_alphabet();
+        let lines = ["cat", "Dog", "cow"].iter().map(|&w| WordLine{word: w.to_string(), original: None, score: None}).collect();
+        let excluded = ["dog", "cat"].iter().map(|w| wordlist::encode_str(w, false).unwrap()).collect();
+        let wordlist = encode_lines(lines, &excluded, false, false);
+        let words:Vec<&[echar::EncodedChar]> = wordlist.words_of_length(3).collect();
+        assert_eq!(words, [wordlist::encode_str("cow", false).unwrap().as_slice()]);
+    }
+
+    #[cfg(feature = "charset-auto")]
+    #[test]
+    /// With --charset, words using chars the file doesn't have are dropped rather than stopping the search.
+    fn charset_file_drops_words() {
+        echar::test_alphabet();
+        let lines = ["ab", "ba", "añ", "ña"].iter().map(|&w| WordLine{word: w.to_string(), original: None, score: None}).collect();
+        let wordlist = encode_lines(lines, &Default::default(), false, true);
+        w2x2::solver::test::assert_results(
+            &plain(),
+            &wordlist,
+            None,
+            &[
+                &["ab", "ba"],
+                &["ba", "ab"],
+            ],
+        );
+    }
+
+    #[cfg(feature = "charset-auto")]
+    #[test]
+    /// The alphabet is every char the wordlist and the words, templates and patterns given use, but not the syntax of the templates and patterns. The narrowest CharSet it fits in is searched with.
+    fn charset_auto() {
+        let lines:Vec<WordLine> = ["Tea", "oat"].iter().map(|&w| WordLine{word: w.to_string(), original: None, score: None}).collect();
+        let options = Options{
+            must_include: vec![("xyz".to_string(), Placement::Anywhere)],
+            templates: Some("[:V:]&#|[^q]&&|&&[:C:]".to_string()),
+            row_patterns: vec![(0, "*ing".to_string())],
+            ..plain()
+        };
+        let mut chars = alphabet_chars(&options, lines.iter());
+        chars.sort_unstable();
+        chars.dedup();
+        // Chars are only downcased once they're made into the alphabet.
+        assert_eq!(chars.into_iter().collect::<String>(), "&Taeginoqtxyz");
+
+        assert_eq!(charset_size_for(26), Some(64));
+        assert_eq!(charset_size_for(64), Some(64));
+        assert_eq!(charset_size_for(65), Some(128));
+        assert_eq!(charset_size_for(100), Some(128));
+        assert_eq!(charset_size_for(129), None);
+    }
+
     #[test]
     /// With no length 2 words available, this should never produce a result. This is a potential edge case because the templates will be completely filled.
     fn must_use_fills_1() {
-        assert_results(
-            &["test", "word"],
-            &["test", "word"],
+        w4x2::solver::test::assert_results(
+            &must_include(&["test", "word"]),
+            &wordlist(&["test", "word"]),
+            None,
             &[],
-        )
+        );
     }
 
-    #[cfg(all(feature = "width-4", feature = "height-2"))]
     #[test]
     /// With some but not all length 2 words available, this should never produce a result. This is a potential edge case because the templates will be completely filled.
     fn must_use_fills_2() {
-        assert_results(
-            &["test", "word", "tw", "sr", "td"],
-            &["test", "word"],
+        w4x2::solver::test::assert_results(
+            &must_include(&["test", "word"]),
+            &wordlist(&["test", "word", "tw", "sr", "td"]),
+            None,
             &[],
-        )
+        );
     }
 
-    #[cfg(all(feature = "width-4", feature = "height-2"))]
     #[test]
     /// With length 2 words available, this should produce 1 result (test|word but not word|test). This is a potential edge case because the templates will be completely filled.
     /// 
@@ -799,15 +1364,43 @@ mod test {
     /// WORD
     /// ```
     fn must_use_fills_3() {
-        assert_results(
-            &["test", "word", "tw", "eo", "sr", "td"],
-            &["test", "word"],
+        w4x2::solver::test::assert_results(
+            &must_include(&["test", "word"]),
+            &wordlist(&["test", "word", "tw", "eo", "sr", "td"]),
+            None,
             &[
                 &[
                     "test",
                     "word",
                 ],
             ],
-        )
+        );
     }
-}
\ No newline at end of file
+
+    #[test]
+    /// test|word and word|test both work, but only one has test as its second row.
+    fn must_use_pinned_row() {
+        w4x2::solver::test::assert_results(
+            &must_include(&["test@row1"]),
+            &wordlist(&["test", "word", "tw", "eo", "sr", "td", "wt", "oe", "rs", "dt"]),
+            None,
+            &[
+                &[
+                    "word",
+                    "test",
+                ],
+            ],
+        );
+    }
+
+    #[test]
+    /// Pinned down, ab has to be one of the columns, so ab|aa (where it's only a row) is left out.
+    fn must_use_pinned_down() {
+        w2x2::solver::test::assert_results(
+            &must_include(&["ab@down"]),
+            &wordlist(&["ab", "ba", "aa"]),
+            None,
+            &[&["aa", "ab"], &["aa", "ba"], &["ab", "ba"], &["ba", "ab"]],
+        );
+    }
+}
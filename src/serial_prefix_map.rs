@@ -1,10 +1,10 @@
 use std::marker::PhantomData;
 use std::ptr::NonNull;
 
-use crate::wordstuffs::*;
-use crate::charset::*;
+use super::wordstuffs::*;
+use super::config::*;
+use super::super::charset::*;
 use crate::echar::*;
-use crate::config::*;
 
 type Offset = u16;
 
@@ -20,33 +20,51 @@ fn line_to_charset(line: Line) -> CharSet {
     res
 }
 
-pub struct SerialPrefixMaps {
-    inner_rows: SingleDimSerialPrefixMap,
-    #[cfg(not(feature = "square"))]
-    inner_cols: SingleDimSerialPrefixMap,
-}
+if_square! {
+    pub struct SerialPrefixMaps {
+        inner_rows: SingleDimSerialPrefixMap,
+        inner_cols: Option<SingleDimSerialPrefixMap>,
+    }
 
-impl SerialPrefixMaps {
-    pub fn new(map: &WordPrefixMap) -> Self {
-        Self {
-            inner_rows: SingleDimSerialPrefixMap::build(map.rows()),
-            #[cfg(not(feature = "square"))]
-            inner_cols: SingleDimSerialPrefixMap::build(map.cols()),
+    impl SerialPrefixMaps {
+        pub fn new(map: &WordPrefixMap) -> Self {
+            Self {
+                inner_rows: SingleDimSerialPrefixMap::build(map.rows()),
+                inner_cols: if map.has_separate_cols() {
+                    Some(SingleDimSerialPrefixMap::build(map.cols()))
+                } else { None },
+            }
+        }
+
+        pub fn cols(&self) -> &SingleDimSerialPrefixMap {
+            self.inner_cols.as_ref().unwrap_or(&self.inner_rows)
         }
     }
+}
 
-    pub fn rows(&self) -> &SingleDimSerialPrefixMap {
-        &self.inner_rows
+if_not_square! {
+    pub struct SerialPrefixMaps {
+        inner_rows: SingleDimSerialPrefixMap,
+        inner_cols: SingleDimSerialPrefixMap,
     }
 
-    #[cfg(feature = "square")]
-    pub fn cols(&self) -> &SingleDimSerialPrefixMap {
-        &self.inner_rows
+    impl SerialPrefixMaps {
+        pub fn new(map: &WordPrefixMap) -> Self {
+            Self {
+                inner_rows: SingleDimSerialPrefixMap::build(map.rows()),
+                inner_cols: SingleDimSerialPrefixMap::build(map.cols()),
+            }
+        }
+
+        pub fn cols(&self) -> &SingleDimSerialPrefixMap {
+            &self.inner_cols
+        }
     }
+}
 
-    #[cfg(not(feature = "square"))]
-    pub fn cols(&self) -> &SingleDimSerialPrefixMap {
-        &self.inner_cols
+impl SerialPrefixMaps {
+    pub fn rows(&self) -> &SingleDimSerialPrefixMap {
+        &self.inner_rows
     }
 }
 
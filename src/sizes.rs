@@ -0,0 +1,279 @@
+// Every supported rectangle size gets its own copy of the size-dependent modules, so that the hot path can still be specialized with the dimensions as constants, while the size itself can be picked at runtime.
+
+use std::io;
+
+use crate::Options;
+use crate::wordlist::Wordlist;
+
+pub const MIN_DIMENSION:usize = 2;
+pub const MAX_DIMENSION:usize = 15;
+
+// These take the place of `#[cfg(feature = "square")]`/`#[cfg(not(feature = "square"))]` inside the per-size modules, since cfgs can't differ between modules.
+macro_rules! keep {
+    ($($t:tt)*) => { $($t)* };
+}
+
+macro_rules! discard {
+    ($($t:tt)*) => {};
+}
+
+pub(crate) use keep;
+pub(crate) use discard;
+
+pub mod square {
+    pub(crate) use super::{keep as if_square, discard as if_not_square};
+}
+
+pub mod rect {
+    pub(crate) use super::{discard as if_square, keep as if_not_square};
+}
+
+macro_rules! sized_modules {
+    ($($name:ident => $width:literal x $height:literal $shape:ident,)*) => {
+        $(
+            pub mod $name {
+                pub mod config {
+                    #[allow(unused_imports)]
+                    pub use super::super::config::*;
+                    pub(crate) use crate::sizes::$shape::*;
+
+                    pub const WORD_SQUARE_WIDTH:usize = $width;
+                    pub const WORD_SQUARE_HEIGHT:usize = $height;
+                    pub const WORD_SQUARE_SIZE:usize = WORD_SQUARE_WIDTH * WORD_SQUARE_HEIGHT;
+
+                    if_square! {
+                        static_assertions::const_assert_eq!(WORD_SQUARE_HEIGHT, WORD_SQUARE_WIDTH);
+                    }
+
+                    if_not_square! {
+                        static_assertions::const_assert_ne!(WORD_SQUARE_HEIGHT, WORD_SQUARE_WIDTH);
+                    }
+                }
+
+                #[macro_use]
+                pub mod wordstuffs {
+                    include!("wordstuffs.rs");
+                }
+
+                #[cfg(feature = "serial")]
+                pub mod serial_prefix_map {
+                    include!("serial_prefix_map.rs");
+                }
+
+                pub mod solver {
+                    include!("solver.rs");
+                }
+            }
+        )*
+
+        pub fn run(
+            width: usize,
+            height: usize,
+            options: &Options,
+            wordlist: &Wordlist,
+            col_wordlist: Option<&Wordlist>,
+        ) -> io::Result<()> {
+            match (width, height) {
+                $(
+                    ($width, $height) => $name::solver::run(options, wordlist, col_wordlist),
+                )*
+                _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Unsupported word rectangle size {}x{}", width, height))),
+            }
+        }
+    };
+}
+
+macro_rules! supported_sizes {
+    ($($name:ident => $width:literal x $height:literal $shape:ident,)*) => {
+        pub fn is_supported(width: usize, height: usize) -> bool {
+            matches!((width, height), $(($width, $height))|*)
+        }
+    };
+}
+
+// Width is always the larger dimension.
+macro_rules! with_sizes {
+    ($callback:ident) => {
+        $callback! {
+            w2x2 => 2 x 2 square,
+            w3x2 => 3 x 2 rect,
+            w3x3 => 3 x 3 square,
+            w4x2 => 4 x 2 rect,
+            w4x3 => 4 x 3 rect,
+            w4x4 => 4 x 4 square,
+            w5x2 => 5 x 2 rect,
+            w5x3 => 5 x 3 rect,
+            w5x4 => 5 x 4 rect,
+            w5x5 => 5 x 5 square,
+            w6x2 => 6 x 2 rect,
+            w6x3 => 6 x 3 rect,
+            w6x4 => 6 x 4 rect,
+            w6x5 => 6 x 5 rect,
+            w6x6 => 6 x 6 square,
+            w7x2 => 7 x 2 rect,
+            w7x3 => 7 x 3 rect,
+            w7x4 => 7 x 4 rect,
+            w7x5 => 7 x 5 rect,
+            w7x6 => 7 x 6 rect,
+            w7x7 => 7 x 7 square,
+            w8x2 => 8 x 2 rect,
+            w8x3 => 8 x 3 rect,
+            w8x4 => 8 x 4 rect,
+            w8x5 => 8 x 5 rect,
+            w8x6 => 8 x 6 rect,
+            w8x7 => 8 x 7 rect,
+            w8x8 => 8 x 8 square,
+            w9x2 => 9 x 2 rect,
+            w9x3 => 9 x 3 rect,
+            w9x4 => 9 x 4 rect,
+            w9x5 => 9 x 5 rect,
+            w9x6 => 9 x 6 rect,
+            w9x7 => 9 x 7 rect,
+            w9x8 => 9 x 8 rect,
+            w9x9 => 9 x 9 square,
+            w10x2 => 10 x 2 rect,
+            w10x3 => 10 x 3 rect,
+            w10x4 => 10 x 4 rect,
+            w10x5 => 10 x 5 rect,
+            w10x6 => 10 x 6 rect,
+            w10x7 => 10 x 7 rect,
+            w10x8 => 10 x 8 rect,
+            w10x9 => 10 x 9 rect,
+            w10x10 => 10 x 10 square,
+            w11x2 => 11 x 2 rect,
+            w11x3 => 11 x 3 rect,
+            w11x4 => 11 x 4 rect,
+            w11x5 => 11 x 5 rect,
+            w11x6 => 11 x 6 rect,
+            w11x7 => 11 x 7 rect,
+            w11x8 => 11 x 8 rect,
+            w11x9 => 11 x 9 rect,
+            w11x10 => 11 x 10 rect,
+            w11x11 => 11 x 11 square,
+            w12x2 => 12 x 2 rect,
+            w12x3 => 12 x 3 rect,
+            w12x4 => 12 x 4 rect,
+            w12x5 => 12 x 5 rect,
+            w12x6 => 12 x 6 rect,
+            w12x7 => 12 x 7 rect,
+            w12x8 => 12 x 8 rect,
+            w12x9 => 12 x 9 rect,
+            w12x10 => 12 x 10 rect,
+            w12x11 => 12 x 11 rect,
+            w12x12 => 12 x 12 square,
+            w13x2 => 13 x 2 rect,
+            w13x3 => 13 x 3 rect,
+            w13x4 => 13 x 4 rect,
+            w13x5 => 13 x 5 rect,
+            w13x6 => 13 x 6 rect,
+            w13x7 => 13 x 7 rect,
+            w13x8 => 13 x 8 rect,
+            w13x9 => 13 x 9 rect,
+            w13x10 => 13 x 10 rect,
+            w13x11 => 13 x 11 rect,
+            w13x12 => 13 x 12 rect,
+            w13x13 => 13 x 13 square,
+            w14x2 => 14 x 2 rect,
+            w14x3 => 14 x 3 rect,
+            w14x4 => 14 x 4 rect,
+            w14x5 => 14 x 5 rect,
+            w14x6 => 14 x 6 rect,
+            w14x7 => 14 x 7 rect,
+            w14x8 => 14 x 8 rect,
+            w14x9 => 14 x 9 rect,
+            w14x10 => 14 x 10 rect,
+            w14x11 => 14 x 11 rect,
+            w14x12 => 14 x 12 rect,
+            w14x13 => 14 x 13 rect,
+            w14x14 => 14 x 14 square,
+            w15x2 => 15 x 2 rect,
+            w15x3 => 15 x 3 rect,
+            w15x4 => 15 x 4 rect,
+            w15x5 => 15 x 5 rect,
+            w15x6 => 15 x 6 rect,
+            w15x7 => 15 x 7 rect,
+            w15x8 => 15 x 8 rect,
+            w15x9 => 15 x 9 rect,
+            w15x10 => 15 x 10 rect,
+            w15x11 => 15 x 11 rect,
+            w15x12 => 15 x 12 rect,
+            w15x13 => 15 x 13 rect,
+            w15x14 => 15 x 14 rect,
+            w15x15 => 15 x 15 square,
+        }
+    };
+}
+
+// The per-size modules find `config`, `charset` and `glob` in their parent. Normally there's only one charset, picked by features.
+#[cfg(not(feature = "charset-auto"))]
+use crate::{config, charset, glob};
+
+#[cfg(not(feature = "charset-auto"))]
+with_sizes!(sized_modules);
+
+// With charset-auto, every size is built once for each width of CharSet, and the smallest one that fits the alphabet of the wordlist is used.
+#[cfg(feature = "charset-auto")]
+macro_rules! charset_width_modules {
+    ($($name:ident => $inner:ty,)*) => {
+        $(
+            pub mod $name {
+                use std::io;
+
+                use crate::Options;
+                use crate::wordlist::Wordlist;
+
+                // Not every size uses everything in config.rs.
+                #[allow(dead_code)]
+                pub mod config {
+                    pub type CharSetInner = $inner;
+                    include!("config.rs");
+                }
+
+                pub mod charset {
+                    include!("charset.rs");
+                }
+
+                pub mod glob {
+                    include!("glob.rs");
+                }
+
+                with_sizes!(sized_modules);
+            }
+        )*
+
+        // The width of the smallest CharSet that an alphabet this long fits in, if any does.
+        pub fn charset_size_for(alphabet_len: usize) -> Option<usize> {
+            [$($name::config::CHAR_SET_SIZE),*].into_iter().find(|&size| alphabet_len <= size)
+        }
+
+        pub fn run(
+            width: usize,
+            height: usize,
+            options: &Options,
+            wordlist: &Wordlist,
+            col_wordlist: Option<&Wordlist>,
+        ) -> io::Result<()> {
+            let alphabet_len = crate::echar::alphabet_len();
+            let size = charset_size_for(alphabet_len);
+            $(
+                if size == Some($name::config::CHAR_SET_SIZE) {
+                    return $name::run(width, height, options, wordlist, col_wordlist);
+                }
+            )*
+            Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Alphabet of {} chars is too big", alphabet_len)))
+        }
+    };
+}
+
+#[cfg(feature = "charset-auto")]
+// Each width is another copy of every size, so there's no u32 one: on 64-bit machines it's no faster than u64, and would only make the build take longer and the binary bigger.
+charset_width_modules! {
+    charset64 => u64,
+    charset128 => u128,
+}
+
+// Tests always use the smallest charset.
+#[cfg(all(test, feature = "charset-auto"))]
+pub use charset64::*;
+
+with_sizes!(supported_sizes);
@@ -0,0 +1,2084 @@
+use std::io::{self, prelude::*};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{self, AtomicBool, AtomicU64, AtomicUsize};
+
+use fnv::FnvHashMap;
+
+use progressing::{
+    Baring,
+    bernoulli::Bar as BernoulliBar,
+};
+
+use super::config::*;
+use super::wordstuffs::*;
+#[cfg(feature = "serial")]
+use super::serial_prefix_map::*;
+use super::super::charset::*;
+use super::super::glob::Glob;
+use crate::echar::*;
+use crate::wordlist::{Wordlist, WordConversionError, ScoreMode};
+use crate::{DEBUG, Options, Placement, SelfSymmetry};
+
+pub fn run(options: &Options, wordlist: &Wordlist, col_wordlist: Option<&Wordlist>) -> io::Result<()> {
+    let &Options{
+        loud,
+        fancy,
+        show_progress,
+        num_threads,
+        count_rects,
+        show_size,
+        ..
+    } = options;
+    let search = match Search::new(options, wordlist, col_wordlist) {
+        Some(search) => search,
+        None => return Ok(()),
+    };
+
+    if loud {
+        eprintln!("Word rectangle order {}x{}", WORD_SQUARE_WIDTH, WORD_SQUARE_HEIGHT);
+        eprintln!("Start: creating index");
+        eprintln!("Starting.");
+    }
+
+    // When searching several sizes at once, each result is tagged with the size it came from.
+    let size_tag = if show_size { format!("{}x{}", WORD_SQUARE_WIDTH, WORD_SQUARE_HEIGHT) } else { String::new() };
+
+    // Rows are shown as they were spelled in the wordlist, before --fold-diacritics.
+    let row_originals:FnvHashMap<WideWord, Vec<char>> = wordlist
+        .originals_of_length(WORD_SQUARE_WIDTH)
+        .map(|(w, original)| (WideWord::from_encoded(w), original.chars().collect()))
+        .collect();
+    let push_row = move |buf:&mut String, wm:WordMatrix, row:RowIndex| {
+        match row_originals.get(&dim_row::index_matrix(wm, row)) {
+            Some(original) => buf.extend(original.iter()),
+            None => buf.extend(ColIndex::all_values().map(|col| -> char { wm[MatrixIndex{row,col}].into() })),
+        }
+    };
+
+    let rect_score = options.score_mode.zip(search.scores.clone()).map(|(mode, scores)| {
+        move |wm:WordMatrix| score_matrix(mode, &scores, wm)
+    });
+
+    let compute_func = move |w2m_rx:std::sync::mpsc::Receiver<WordMatrix>| {
+        if fancy {
+            let mut minibuffer = String::new();
+            while let Ok(wm) = w2m_rx.recv() {
+                if show_size {
+                    minibuffer.push_str(&size_tag);
+                    minibuffer.push('\n');
+                }
+                for row in RowIndex::all_values() {
+                    push_row(&mut minibuffer, wm, row);
+                    minibuffer.push('\n');
+                }
+                if let Some(rect_score) = &rect_score {
+                    minibuffer.push_str(&format!("score {}\n", rect_score(wm)));
+                }
+                println!("{}", minibuffer);
+                minibuffer.truncate(0);
+            }
+            Ok(())
+        } else {
+            let mut minibuffer = String::new();
+            let mut writer = std::io::BufWriter::with_capacity(1024*1024, std::io::stdout());
+            while let Ok(wm) = w2m_rx.recv() {
+                if show_size {
+                    minibuffer.push_str(&size_tag);
+                    minibuffer.push(' ');
+                }
+                for row in RowIndex::all_values() {
+                    push_row(&mut minibuffer, wm, row);
+                    if row < RowIndex::MAX {
+                        minibuffer.push('|');
+                    }
+                }
+                if let Some(rect_score) = &rect_score {
+                    minibuffer.push_str(&format!("\t{}", rect_score(wm)));
+                }
+                minibuffer.push('\n');
+                writer.write_all(minibuffer.as_bytes())?;
+                minibuffer.truncate(0);
+            }
+            writer.flush()?;
+            Ok(())
+        }
+    };
+
+    let strategy = search.strategy.clone();
+
+    let mut time = devtimer::DevTime::new_simple();
+    time.start();
+
+    let count = search.outer_compute(num_threads, compute_func, show_progress, count_rects);
+
+    time.stop();
+    if loud {
+        eprintln!("Took {} secs", (time.time_in_micros().unwrap() as u64 as f64) / 1_000_000.0);
+        if let Some(counts) = &strategy.lookahead {
+            eprintln!(
+                "Lookahead cut off {} of {} steps, with {} extra prefix map lookups",
+                counts.cut.load(atomic::Ordering::Relaxed),
+                counts.steps.load(atomic::Ordering::Relaxed),
+                counts.lookups.load(atomic::Ordering::Relaxed),
+            );
+        }
+    }
+    if count_rects {
+        if show_size {
+            println!("{}x{}: {} rects found", WORD_SQUARE_WIDTH, WORD_SQUARE_HEIGHT, count);
+        } else {
+            println!("{} rects found", count);
+        }
+    }
+
+    Ok(())
+}
+
+// Everything `outer_compute` needs, worked out from the options and wordlists. Tests search with the same setup `run` does.
+pub struct Search {
+    words: TheSet<EitherWord>,
+    col_words: Option<TheSet<EitherWord>>,
+    templates: Vec<WordMatrix>,
+    // One for each template, or None when no template has a class.
+    cell_masks: Option<Vec<CellMasks>>,
+    scores: Option<Arc<Scores>>,
+    top: Option<Arc<TopK>>,
+    restrictions: Restrictions,
+    strategy: Strategy,
+}
+
+impl Search {
+    // None when there's nothing to search, and --ignore-empty-wordlist says that's fine.
+    pub fn new(options: &Options, wordlist: &Wordlist, col_wordlist: Option<&Wordlist>) -> Option<Search> {
+        let ignore_empty_wordlist = options.ignore_empty_wordlist;
+        let mut restrictions = Restrictions{
+            distinct: options.distinct,
+            symmetric: options.symmetric,
+            invariant_under: options.self_symmetric.iter().map(|&s| s.into()).collect(),
+            diagonal: options.diagonal,
+            anti_diagonal: options.anti_diagonal,
+            canonical: options.canonical,
+            symmetry_group: options.symmetry_group,
+            line_patterns: (parse_line_patterns(&options.row_patterns, "row"), parse_line_patterns(&options.col_patterns, "col")),
+        };
+        // Row i and column i are the same word in a symmetric square, so it has to match the patterns for both.
+        if restrictions.symmetric {
+            let (rows, cols) = &mut restrictions.line_patterns;
+            let all:Vec<(usize, Glob)> = rows.iter().chain(cols.iter()).cloned().collect();
+            *rows = all.clone();
+            *cols = all;
+        }
+
+        let mut words:TheSet<EitherWord> = Default::default();
+
+        for w in wordlist.words_of_length(WORD_SQUARE_WIDTH) {
+            words.insert(WideWord::from_encoded(w).into());
+        }
+        if_not_square! {
+            for w in col_wordlist.unwrap_or(wordlist).words_of_length(WORD_SQUARE_HEIGHT) {
+                words.insert(TallWord::from_encoded(w).into());
+            }
+        }
+        // Rows and columns of rects never have the same length, so only squares need to keep the column words separately.
+        let mut col_words:Option<TheSet<EitherWord>> = col_wordlist
+            .filter(|_| WORD_SQUARE_WIDTH == WORD_SQUARE_HEIGHT)
+            .map(|l| l.words_of_length(WORD_SQUARE_HEIGHT).map(|w| TallWord::from_encoded(w).into()).collect());
+
+        // This is purposefully *not* a hashset, a word that appears twice in the must_include list must appear twice in any result word rectangles.
+        let mut must_include:Vec<(EitherWord, Placement)> = Vec::new();
+
+        for &(ref include_str, placement) in &options.must_include {
+            // A word pinned across or down has to be as long as the rows or columns.
+            let fits_placement = |word: EitherWord| match placement {
+                Placement::Anywhere => true,
+                Placement::Across | Placement::Row(_) => word.wide().is_some(),
+                Placement::Down | Placement::Col(_) => word.tall().is_some(),
+            };
+            match EitherWord::from_str_with_nulls(include_str.as_str()).and_then(|w| if fits_placement(w) { Ok(w) } else { Err(WordConversionError::WrongLength) }) {
+                Ok(word) => {
+                    must_include.push((word, placement));
+                    words.insert(word);
+                    if let Some(col_words) = &mut col_words {
+                        col_words.insert(word);
+                    }
+                },
+                Err(WordConversionError::WrongLength) => {
+                    if ignore_empty_wordlist {
+                        return None;
+                    } else {
+                        panic!("Must-use word {:?} length do not match dimensions.", include_str);
+                    }
+                },
+                Err(e @ WordConversionError::UnencodeableChar(_,_)) => {
+                    panic!("Error encoding must-use word {:?} due to {:?}", include_str, e);
+                },
+                Err(WordConversionError::NullChar) => unreachable!(),
+            }
+        }
+
+        assert_eq!(must_include.len(), options.must_include.len());
+
+        if options.show_progress && !must_include.is_empty() {
+            eprintln!("ERR: Cannot use both show-progress and must-use together.");
+            std::process::exit(1);
+        }
+
+        let mut cell_masks:Vec<CellMasks> = Vec::new();
+        let templates = if let Some(arg_templates) = &options.templates {
+            let mut res = vec![];
+            let thing:Vec<Vec<&str>> = arg_templates.split('!').map(|s| s.split('|').collect()).collect();
+            for (i, rect) in thing.into_iter().enumerate() {
+                if rect.len() != WORD_SQUARE_HEIGHT {
+                    eprintln!("Error: Incorrect number of words in template {}", i);
+                    std::process::exit(1);
+                }
+                let mut m = WordMatrix::default();
+                let mut masks = full_masks();
+                for (j, word) in rect.into_iter().enumerate() {
+                    let cells = parse_template_cells(word).unwrap_or_else(|e| {
+                        eprintln!("Error: {} in word {} in template {}", e, j, i);
+                        std::process::exit(1);
+                    });
+                    if cells.len() != WORD_SQUARE_WIDTH {
+                        eprintln!("Error: Incorrect number of letters in word {} in template {}", j, i);
+                        std::process::exit(1);
+                    }
+                    let row:RowIndex = j.try_into().unwrap();
+                    for (k, (e, mask)) in cells.into_iter().enumerate() {
+                        let col:ColIndex = k.try_into().unwrap();
+                        m[MatrixIndex{row, col}] = e;
+                        if let Some(mask) = mask {
+                            masks[MatrixIndex{row, col}] = mask;
+                        }
+                    }
+                }
+
+                res.push(m);
+                cell_masks.push(masks);
+            }
+            res
+        } else {
+            cell_masks.push(full_masks());
+            vec![Default::default()]
+        };
+        let has_classes = cell_masks.iter().any(|m| *m != full_masks());
+
+        // Blocks split rows and columns into shorter slots, which are filled with shorter words padded out with blocks.
+        if templates.iter().any(|t| t.0.contains(&BLOCK_CHAR)) {
+            for len in 2..WORD_SQUARE_WIDTH {
+                for w in wordlist.words_of_length(len) {
+                    words.insert(WideWord::padded(w).into());
+                }
+            }
+            if_not_square! {
+                for len in 2..WORD_SQUARE_HEIGHT {
+                    for w in col_wordlist.unwrap_or(wordlist).words_of_length(len) {
+                        words.insert(TallWord::padded(w).into());
+                    }
+                }
+            }
+            if let Some(col_words) = &mut col_words {
+                for len in 2..WORD_SQUARE_HEIGHT {
+                    for w in col_wordlist.unwrap().words_of_length(len) {
+                        col_words.insert(TallWord::padded(w).into());
+                    }
+                }
+            }
+        }
+
+        // Must-include words can only go where every letter is allowed by its cell's class.
+        let mut templates:Vec<(WordMatrix, CellMasks)> = templates.into_iter().zip(cell_masks).flat_map(|(template, masks)| {
+            make_templates(must_include.as_slice(), vec![template])
+                .into_iter()
+                .filter(move |t| fits_masks(t, &masks))
+                .map(move |t| (t, masks))
+        }).collect();
+        let invariants = restrictions.invariants();
+        if !invariants.is_empty() {
+            // A word placed as a row and the same word placed as that column make the same symmetric template.
+            let ties = make_ties(&invariants);
+            templates = templates.into_iter().filter_map(|(t, masks)| {
+                let t = symmetrize(t, &ties)?;
+                let masks = tie_masks(masks, &ties);
+                fits_masks(&t, &masks).then(|| (t, masks))
+            }).collect();
+            templates.sort();
+            templates.dedup();
+        }
+        let (templates, cell_masks):(Vec<WordMatrix>, Vec<CellMasks>) = templates.into_iter().unzip();
+
+        if DEBUG {
+            dbg!(&templates);
+        }
+
+        if templates.is_empty() {
+            if ignore_empty_wordlist {
+                return None;
+            } else {
+                panic!("must-use words can not be fit together.");
+            }
+        }
+
+        if !ignore_empty_wordlist && (words.is_empty() || col_words.as_ref().map_or(false, TheSet::is_empty)) {
+            panic!("No words in wordlist!");
+        }
+
+        let scores = options.score_mode.map(|_| Arc::new((
+            word_scores::<WORD_SQUARE_WIDTH>(wordlist),
+            word_scores::<WORD_SQUARE_HEIGHT>(col_wordlist.unwrap_or(wordlist)),
+        )));
+        let top = options.top.map(|k| {
+            let mode = options.score_mode.expect("--top needs a wordlist with scores");
+            Arc::new(TopK::new(k, mode, Arc::clone(scores.as_ref().unwrap())))
+        });
+
+        let strategy = Strategy{
+            dynamic_order: options.dynamic_order,
+            lookahead: options.lookahead.then(Default::default),
+            split_depth: options.split_depth,
+        };
+
+        Some(Search{
+            words,
+            col_words,
+            templates,
+            cell_masks: has_classes.then(|| cell_masks),
+            scores,
+            top,
+            restrictions,
+            strategy,
+        })
+    }
+
+    pub fn outer_compute(
+        self,
+        num_threads: usize,
+        output_func: impl 'static + Send + FnOnce(std::sync::mpsc::Receiver<WordMatrix>) -> Result<(), std::io::Error>,
+        show_progress: bool,
+        count_rects: bool,
+    ) -> u64 {
+        outer_compute(
+            self.words,
+            self.col_words,
+            self.templates.as_slice(),
+            self.cell_masks.as_deref(),
+            num_threads,
+            output_func,
+            show_progress,
+            count_rects,
+            self.top,
+            self.restrictions,
+            self.strategy,
+        )
+    }
+}
+
+pub type Scores = (FnvHashMap<WideWord, f64>, FnvHashMap<TallWord, f64>);
+
+// When a word is in the wordlist more than once, its best score is used. Shorter words are padded out with blocks, as they are to fill the slots between blocks.
+fn word_scores<const N:usize>(wordlist: &Wordlist) -> FnvHashMap<Word<N>, f64> {
+    let mut res:FnvHashMap<Word<N>, f64> = Default::default();
+    for len in 2..=N {
+        for (w, score) in wordlist.scored_words_of_length(len) {
+            let best = res.entry(Word::padded(w)).or_insert(score);
+            *best = best.max(score);
+        }
+    }
+    res
+}
+
+// The words a line is made of: the whole line, or with blocks, each slot between them that's longer than a single cell.
+fn line_words<const N:usize>(line: Word<N>) -> impl Iterator<Item = Word<N>> {
+    line.slots().filter(|w| !w.is_single_cell())
+}
+
+// Words that aren't in the wordlist, such as must-include words, count as 0.
+fn score_matrix(
+    mode: ScoreMode,
+    scores: &Scores,
+    matrix: WordMatrix,
+) -> f64 {
+    let (row_score, col_score) = each_dimension!(dim, {
+        mode.combine(dim::Index::all_values().flat_map(|i| line_words(dim::index_matrix(matrix, i))).map(|w| {
+            dim::index_tuple(scores).get(&w).copied().unwrap_or(0.0)
+        }))
+    });
+    mode.combine([row_score, col_score])
+}
+
+// For every key of the prefix map made from the same template, the best score of any word it could still become.
+fn make_score_bounds(
+    template: WordMatrix,
+    wordlist: &TheSet<EitherWord>,
+    col_wordlist: Option<&TheSet<EitherWord>>,
+    scores: &Scores,
+) -> Scores {
+    let wordlists = (wordlist, col_wordlist.unwrap_or(wordlist));
+    let mut res:Scores = Default::default();
+    each_dimension!(dim, {
+        let mut word_templates:Vec<_> = dim::Index::all_values().flat_map(|i| line_words(dim::index_matrix(template, i))).collect();
+        word_templates.sort();
+        word_templates.dedup();
+        let my_res = dim::index_tuple_mut(&mut res);
+        for &w in *dim::index_tuple(&wordlists) {
+            if let Some(w) = dim::get_from_either(w) {
+                let score = dim::index_tuple(scores).get(&w).copied().unwrap_or(0.0);
+                for &word_template in &word_templates {
+                    if word_template.is_match(w) {
+                        for (prefix, _) in w.prefixes(word_template) {
+                            let best = my_res.entry(prefix).or_insert(score);
+                            *best = best.max(score);
+                        }
+                    }
+                }
+            }
+        }
+    });
+    res
+}
+
+// The best score any rectangle filling in the rest of the matrix could have, counting finished words at their own score and unfinished ones at the best word they could still become. None if some word can't be finished at all.
+fn score_bound(
+    mode: ScoreMode,
+    scores: &Scores,
+    bounds: &Scores,
+    matrix: &WordMatrix,
+) -> Option<f64> {
+    let mut dead_end = false;
+    let (row_bound, col_bound) = each_dimension!(dim, {
+        mode.combine(dim::Index::all_values().flat_map(|i| line_words(dim::index_matrix(*matrix, i))).map(|word| {
+            if word.contains(&NULL_CHAR) {
+                dim::index_tuple(bounds).get(&word).copied().unwrap_or_else(|| { dead_end = true; 0.0 })
+            } else {
+                dim::index_tuple(scores).get(&word).copied().unwrap_or(0.0)
+            }
+        }))
+    });
+    if dead_end { None } else { Some(mode.combine([row_bound, col_bound])) }
+}
+
+// Whether the words across and down through `at_idx` are each different from every other word in the matrix, if they're finished. With blocks, each slot between them is a word, and single cells aren't words at all.
+fn no_repeated_words(matrix: &WordMatrix, at_idx: MatrixIndex) -> bool {
+    let (row_ok, col_ok) = each_dimension!(dim, {
+        let word = dim::get_slot_intersecting_point(*matrix, at_idx);
+        word.is_single_cell() || word.contains(&NULL_CHAR) || {
+            // A slot across can repeat a slot down of the same length, in a rect as well as a square. This counts `word` itself once.
+            let (in_rows, in_cols) = each_dimension!(other, {
+                other::Index::all_values()
+                    .flat_map(|i| line_words(other::index_matrix(*matrix, i)))
+                    .filter(|w| w.unpadded() == word.unpadded())
+                    .count()
+            });
+            in_rows + in_cols == 1
+        }
+    });
+    row_ok && col_ok
+}
+
+// A way of flipping and/or transposing a rect that gives a rect of the same size. Transposing only keeps the size of a square, and flipping both ways is the same as turning it 180°.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct Symmetry {
+    pub transpose: bool,
+    // Reverses the order of the rows, so every column reads backwards.
+    pub flip_rows: bool,
+    // Reverses the order of the columns, so every row reads backwards.
+    pub flip_cols: bool,
+}
+
+impl Symmetry {
+    pub const TRANSPOSE:Self = Symmetry{transpose: true, flip_rows: false, flip_cols: false};
+    pub const ROTATE_180:Self = Symmetry{transpose: false, flip_rows: true, flip_cols: true};
+
+    // Every symmetry of this size except the one that changes nothing.
+    pub fn all() -> impl Iterator<Item = Self> {
+        let transposes:&[bool] = if WORD_SQUARE_WIDTH == WORD_SQUARE_HEIGHT { &[false, true] } else { &[false] };
+        transposes.iter().flat_map(|&transpose| {
+            [false, true].into_iter().flat_map(move |flip_rows| {
+                [false, true].into_iter().map(move |flip_cols| Symmetry{transpose, flip_rows, flip_cols})
+            })
+        }).skip(1)
+    }
+
+    // The cell that ends up at `mi`.
+    fn source(self, mi: MatrixIndex) -> MatrixIndex {
+        let mut res = if self.transpose { mi.mirrored() } else { mi };
+        if self.flip_rows {
+            res.row = (WORD_SQUARE_HEIGHT - 1 - res.row.into():usize).try_into().unwrap();
+        }
+        if self.flip_cols {
+            res.col = (WORD_SQUARE_WIDTH - 1 - res.col.into():usize).try_into().unwrap();
+        }
+        res
+    }
+
+    fn apply<T: Copy>(self, matrix: &GenericMatrix<T>) -> GenericMatrix<T> {
+        let mut res = *matrix;
+        for row in RowIndex::all_values() {
+            for col in ColIndex::all_values() {
+                let mi = MatrixIndex{row, col};
+                res[mi] = matrix[self.source(mi)];
+            }
+        }
+        res
+    }
+
+    fn commutes_with(self, other: Self) -> bool {
+        RowIndex::all_values().all(|row| ColIndex::all_values().all(|col| {
+            let mi = MatrixIndex{row, col};
+            self.source(other.source(mi)) == other.source(self.source(mi))
+        }))
+    }
+
+    // Whether the matrix comes no later than what this symmetry makes of it, reading both row by row, or could still once it's filled in.
+    fn comes_first(self, matrix: &WordMatrix) -> bool {
+        for row in RowIndex::all_values() {
+            for col in ColIndex::all_values() {
+                let mi = MatrixIndex{row, col};
+                let (mine, theirs) = (matrix[mi], matrix[self.source(mi)]);
+                if mine == NULL_CHAR || theirs == NULL_CHAR {
+                    return true
+                }
+                if mine != theirs {
+                    return mine < theirs
+                }
+            }
+        }
+        true
+    }
+}
+
+impl From<SelfSymmetry> for Symmetry {
+    fn from(s: SelfSymmetry) -> Self {
+        match s {
+            SelfSymmetry::Rotate180 => Symmetry::ROTATE_180,
+            SelfSymmetry::Rotate90 => Symmetry{transpose: true, flip_rows: false, flip_cols: true},
+            SelfSymmetry::MirrorLeftRight => Symmetry{transpose: false, flip_rows: false, flip_cols: true},
+            SelfSymmetry::MirrorTopBottom => Symmetry{transpose: false, flip_rows: true, flip_cols: false},
+        }
+    }
+}
+
+// Ties every cell to the first one filled in out of all the cells these symmetries map it to, so a search for rects that are the same under them only has to pick a letter for that one.
+fn make_ties(symmetries: &[Symmetry]) -> GenericMatrix<MatrixIndex> {
+    let mut ties = GenericMatrix([MatrixIndex::ZERO; WORD_SQUARE_SIZE]);
+    let mut seen = GenericMatrix([false; WORD_SQUARE_SIZE]);
+    let mut next = Some(MatrixIndex::ZERO);
+    while let Some(first) = next {
+        if !seen[first] {
+            seen[first] = true;
+            let mut to_visit = vec![first];
+            while let Some(mi) = to_visit.pop() {
+                ties[mi] = first;
+                for s in symmetries {
+                    let other = s.source(mi);
+                    if !seen[other] {
+                        seen[other] = true;
+                        to_visit.push(other);
+                    }
+                }
+            }
+        }
+        next = first.inc();
+    }
+    ties
+}
+
+// Whether every row and column is in its wordlist. Rows and columns with blocks are checked slot by slot, leaving out single cells.
+fn words_valid(matrix: &WordMatrix, wordlists: (&TheSet<EitherWord>, &TheSet<EitherWord>)) -> bool {
+    let (rows_valid, cols_valid) = each_dimension!(dim, {
+        dim::Index::all_values().all(|i| {
+            let word = dim::index_matrix(*matrix, i);
+            if word.has_blocks() {
+                word.slots().all(|slot| slot.is_single_cell() || dim::index_tuple(&wordlists).contains(&slot.into()))
+            } else {
+                dim::index_tuple(&wordlists).contains(&word.into())
+            }
+        })
+    });
+    rows_valid && cols_valid
+}
+
+// Whether each of these diagonals spells a word from the row wordlist.
+fn diagonals_valid(matrix: &WordMatrix, wordlist: &TheSet<EitherWord>, diagonals: &[Vec<MatrixIndex>]) -> bool {
+    diagonals.iter().all(|line| {
+        let word:Vec<EncodedChar> = line.iter().map(|&mi| matrix[mi]).collect();
+        wordlist.contains(&WideWord::from_encoded(&word).into())
+    })
+}
+
+// Whether a whole matrix would be found by the search: all its words are in the wordlists, it fits one of the templates, and it's the same under every symmetry it has to be.
+fn is_result(
+    matrix: &WordMatrix,
+    wordlists: (&TheSet<EitherWord>, &TheSet<EitherWord>),
+    templates: &[WordMatrix],
+    cell_masks: Option<&[CellMasks]>,
+    invariants: &[Symmetry],
+    diagonals: &[Vec<MatrixIndex>],
+    line_patterns: &(Vec<(usize, Glob)>, Vec<(usize, Glob)>),
+) -> bool {
+    words_valid(matrix, wordlists)
+        && diagonals_valid(matrix, wordlists.0, diagonals)
+        && lines_match(matrix, line_patterns)
+        && templates.iter().enumerate().any(|(i, t)| {
+            t.0.iter().zip(matrix.0.iter()).all(|(&t, &c)| t == NULL_CHAR || t == c)
+                && cell_masks.map_or(true, |masks| fits_masks(matrix, &masks[i]))
+        })
+        && invariants.iter().all(|s| s.apply(matrix) == *matrix)
+}
+
+#[derive(Debug,Clone,Copy)]
+struct ScoredMatrix(f64, WordMatrix);
+
+impl PartialEq for ScoredMatrix {
+    fn eq(&self, other: &Self) -> bool { self.cmp(other) == Ordering::Equal }
+}
+
+impl Eq for ScoredMatrix {}
+
+impl PartialOrd for ScoredMatrix {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl Ord for ScoredMatrix {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then_with(|| self.1.cmp(&other.1))
+    }
+}
+
+// The best `k` results found so far for --top, shared by every worker thread. The search goes in its usual order and only uses this to cut off partial matrices that can't beat them, so how much it cuts depends on how soon good results turn up.
+pub struct TopK {
+    k: usize,
+    mode: ScoreMode,
+    scores: Arc<Scores>,
+    best: Mutex<BinaryHeap<Reverse<ScoredMatrix>>>,
+    // The score of the worst of the best `k` once there are `k` of them, stored as f64 bits so workers can check it without locking.
+    threshold: AtomicU64,
+}
+
+impl TopK {
+    pub fn new(k: usize, mode: ScoreMode, scores: Arc<Scores>) -> Self {
+        Self{
+            k,
+            mode,
+            scores,
+            best: Default::default(),
+            threshold: AtomicU64::new(f64::NEG_INFINITY.to_bits()),
+        }
+    }
+
+    fn may_improve(&self, bounds: &Scores, matrix: &WordMatrix) -> bool {
+        let threshold = f64::from_bits(self.threshold.load(atomic::Ordering::Relaxed));
+        score_bound(self.mode, &self.scores, bounds, matrix).map_or(false, |bound| bound > threshold)
+    }
+
+    fn offer(&self, matrix: WordMatrix) {
+        let scored = ScoredMatrix(score_matrix(self.mode, &self.scores, matrix), matrix);
+        let mut best = self.best.lock().unwrap();
+        if best.len() < self.k {
+            best.push(Reverse(scored));
+        } else if scored.0 > best.peek().unwrap().0.0 {
+            best.pop();
+            best.push(Reverse(scored));
+        } else {
+            return
+        }
+        if best.len() == self.k {
+            self.threshold.store(best.peek().unwrap().0.0.to_bits(), atomic::Ordering::Relaxed);
+        }
+    }
+
+    // Best first.
+    fn take_best(&self) -> Vec<WordMatrix> {
+        let best = std::mem::take(&mut *self.best.lock().unwrap());
+        best.into_sorted_vec().into_iter().map(|Reverse(scored)| scored.1).collect()
+    }
+}
+
+// The jobs for one template, shared by every worker thread. Each thread has a deque of jobs it split off its own search for threads that ran out of work, and takes the newest back off the end itself, while the others steal the oldest, and so likely biggest, from the front.
+pub struct WorkQueue {
+    deques: Vec<Mutex<VecDeque<WordMatrix>>>,
+    // Threads out of work once the main thread is done sending jobs.
+    idle: AtomicUsize,
+    // Jobs sitting in the deques.
+    queued: AtomicUsize,
+    // Jobs sent by the main thread or split off that aren't finished yet. The main thread adds its own before sending them, so this only gets to 0 once everything is done.
+    pending: AtomicUsize,
+    // Set once the main thread has sent every job.
+    closed: AtomicBool,
+    // Threads with nothing to do wait on `wakeup` until a job is given or sent, or everything is done. Held while checking for that, so none of them miss being woken.
+    lock: Mutex<()>,
+    wakeup: Condvar,
+}
+
+impl WorkQueue {
+    pub fn new(num_threads: usize) -> Self {
+        Self{
+            deques: (0..num_threads).map(|_| Default::default()).collect(),
+            idle: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+            pending: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+            lock: Mutex::new(()),
+            wakeup: Condvar::new(),
+        }
+    }
+
+    // Checked at every step, so it has to be cheap.
+    fn wants_work(&self) -> bool {
+        self.idle.load(atomic::Ordering::Relaxed) > self.queued.load(atomic::Ordering::Relaxed)
+    }
+
+    fn wake(&self, all: bool) {
+        drop(self.lock.lock().unwrap());
+        if all {
+            self.wakeup.notify_all();
+        } else {
+            self.wakeup.notify_one();
+        }
+    }
+
+    // For the main thread, to hand a job to whichever worker takes it first.
+    fn send(&self, tx: &crossbeam_channel::Sender<WordMatrix>, job: WordMatrix) {
+        self.pending.fetch_add(1, atomic::Ordering::SeqCst);
+        tx.send(job).unwrap();
+        self.wake(false);
+    }
+
+    // For the main thread, once it's sent every job.
+    fn close(&self) {
+        self.closed.store(true, atomic::Ordering::SeqCst);
+        self.wake(true);
+    }
+
+    fn give(&self, me: usize, job: WordMatrix) {
+        self.pending.fetch_add(1, atomic::Ordering::SeqCst);
+        self.queued.fetch_add(1, atomic::Ordering::Relaxed);
+        self.deques[me].lock().unwrap().push_back(job);
+        self.wake(false);
+    }
+
+    fn finish(&self) {
+        if self.pending.fetch_sub(1, atomic::Ordering::SeqCst) == 1 {
+            self.wake(true);
+        }
+    }
+
+    fn take(&self, i: usize, pop: impl FnOnce(&mut VecDeque<WordMatrix>) -> Option<WordMatrix>) -> Option<WordMatrix> {
+        let job = pop(&mut self.deques[i].lock().unwrap())?;
+        self.queued.fetch_sub(1, atomic::Ordering::Relaxed);
+        Some(job)
+    }
+
+    // The next job for thread `me`, and whether it came from the main thread: its own newest, then the main thread's next, then one stolen from another thread. Waits for one if there's none yet, and gives None once the main thread is done and every job is finished.
+    fn next(&self, me: usize, rx: &crossbeam_channel::Receiver<WordMatrix>) -> Option<(WordMatrix, bool)> {
+        let mut idle = false;
+        let res = loop {
+            if let Some(job) = self.take(me, VecDeque::pop_back) { break Some((job, false)) }
+            if let Ok(job) = rx.try_recv() { break Some((job, true)) }
+            let mut others = (1..self.deques.len()).map(|i| (me + i) % self.deques.len());
+            if let Some(job) = others.find_map(|i| self.take(i, VecDeque::pop_front)) { break Some((job, false)) }
+            let guard = self.lock.lock().unwrap();
+            let closed = self.closed.load(atomic::Ordering::SeqCst);
+            if closed && self.pending.load(atomic::Ordering::SeqCst) == 0 { break None }
+            // While the main thread is still sending jobs, its next one is on the way, so there's no need for others to split theirs up yet.
+            if closed && !idle {
+                idle = true;
+                self.idle.fetch_add(1, atomic::Ordering::Relaxed);
+            }
+            // Checked again with the lock held, in case a job came in since the checks above.
+            if self.queued.load(atomic::Ordering::Relaxed) == 0 && rx.is_empty() {
+                drop(self.wakeup.wait(guard).unwrap());
+            }
+        };
+        if idle {
+            self.idle.fetch_sub(1, atomic::Ordering::Relaxed);
+        }
+        res
+    }
+}
+
+// Copies every letter to all the cells it's tied to, or None if two tied cells have different letters.
+fn symmetrize(template: WordMatrix, ties: &GenericMatrix<MatrixIndex>) -> Option<WordMatrix> {
+    let mut res = template;
+    let all_cells = || RowIndex::all_values().flat_map(|row| ColIndex::all_values().map(move |col| MatrixIndex{row, col}));
+    // Gather every letter onto the first cell of its tie, then copy them back out.
+    for mi in all_cells() {
+        if template[mi] != NULL_CHAR {
+            let first = &mut res[ties[mi]];
+            if *first != NULL_CHAR && *first != template[mi] {
+                return None
+            }
+            *first = template[mi];
+        }
+    }
+    for mi in all_cells() {
+        res[mi] = res[ties[mi]];
+    }
+    Some(res)
+}
+
+// Which rects are found beyond what the wordlist allows. These are checked as the search goes, rather than filtered out afterwards.
+#[derive(Debug,Clone,Default)]
+pub struct Restrictions {
+    // No word is used twice.
+    pub distinct: bool,
+    // Squares that read the same across and down, searching only the cells on and above the diagonal.
+    pub symmetric: bool,
+    // Rects that are the same under each of these, searching only one of each set of cells they tie together.
+    pub invariant_under: Vec<Symmetry>,
+    // Only one square out of each square and its transpose.
+    pub canonical: bool,
+    // Only one rect out of each set that are the same flipped, turned or (for squares) transposed.
+    pub symmetry_group: bool,
+    // Squares whose diagonal from the top left spells a word from the row wordlist, read top to bottom.
+    pub diagonal: bool,
+    // Squares whose diagonal from the top right spells a word from the row wordlist, read top to bottom.
+    pub anti_diagonal: bool,
+    // Globs that every word in the given row (or column) has to match, by index.
+    pub line_patterns: (Vec<(usize, Glob)>, Vec<(usize, Glob)>),
+}
+
+// How the search goes about finding rects. None of this changes which rects are found, only how fast.
+#[derive(Debug,Clone,Default)]
+pub struct Strategy {
+    // Fill in whichever open cell has the fewest letters that fit next, rather than going row by row (or weaving). Not for the serial prefix map.
+    #[allow(dead_code)]
+    pub dynamic_order: bool,
+    // After each cell is filled in, check that its row and column can still be finished, and count how that went. Not for the serial prefix map.
+    pub lookahead: Option<Arc<LookaheadCounts>>,
+    // How many of a template's empty cells the main thread fills in before handing off to the worker threads, or None to pick with pick_split_depth.
+    pub split_depth: Option<usize>,
+}
+
+// What Strategy::lookahead did, added up over every thread once each is done with a template.
+#[derive(Debug,Default)]
+pub struct LookaheadCounts {
+    // Cells filled in that fit where they were, and so were looked ahead from.
+    pub steps: AtomicU64,
+    // Steps that were cut off because a row or column couldn't be finished.
+    pub cut: AtomicU64,
+    // Prefix map lookups made looking ahead, on top of the usual ones.
+    pub lookups: AtomicU64,
+}
+
+impl Restrictions {
+    // Every symmetry a result has to be the same under.
+    fn invariants(&self) -> Vec<Symmetry> {
+        let mut res = self.invariant_under.clone();
+        if self.symmetric {
+            res.push(Symmetry::TRANSPOSE);
+        }
+        res
+    }
+
+    // Whether each of dim_diag and dim_anti has to spell a word. Unused for non-square sizes, which have no diagonals.
+    #[allow(dead_code)]
+    fn wanted_diagonals(&self) -> [bool; 2] {
+        [self.diagonal, self.anti_diagonal]
+    }
+
+    // The cells of each diagonal that has to spell a word, top to bottom.
+    fn diagonal_lines(&self) -> Vec<Vec<MatrixIndex>> {
+        #[allow(unused_mut)]
+        let mut res = Vec::new();
+        if_square! {
+            each_diagonal!(dim, {
+                if self.wanted_diagonals()[dim::DIMENSION_ID - dim_diag::DIMENSION_ID] {
+                    res.push(dim::cells().collect());
+                }
+            });
+        }
+        res
+    }
+}
+
+fn parse_line_patterns(patterns: &[(usize, String)], flag: &str) -> Vec<(usize, Glob)> {
+    patterns.iter().map(|(i, s)| {
+        let glob = s.parse().unwrap_or_else(|e| {
+            eprintln!("Error: {} in --{} {}={}", e, flag, i, s);
+            std::process::exit(1);
+        });
+        (*i, glob)
+    }).collect()
+}
+
+// Whether every word in a row or column with patterns matches them. With blocks, that's each word between the blocks.
+fn lines_match(matrix: &WordMatrix, line_patterns: &(Vec<(usize, Glob)>, Vec<(usize, Glob)>)) -> bool {
+    let (rows_match, cols_match) = each_dimension!(dim, {
+        dim::index_tuple(line_patterns).iter().all(|(i, glob)| {
+            let word = dim::index_matrix(*matrix, (*i).try_into().unwrap());
+            if word.has_blocks() {
+                word.slots().all(|slot| slot.is_single_cell() || glob.matches(&slot.0))
+            } else {
+                glob.matches(&word.0)
+            }
+        })
+    });
+    rows_match && cols_match
+}
+
+// Which letters each cell of a template allows. Cells without a class allow every letter.
+pub type CellMasks = GenericMatrix<CharSet>;
+
+fn full_masks() -> CellMasks {
+    GenericMatrix([CharSet::full(); WORD_SQUARE_SIZE])
+}
+
+// Whether every letter in the matrix is allowed in its cell.
+fn fits_masks(matrix: &WordMatrix, masks: &CellMasks) -> bool {
+    matrix.0.iter().zip(masks.0.iter()).all(|(&c, mask)| c == NULL_CHAR || c == BLOCK_CHAR || mask.has(c))
+}
+
+// Tied cells always hold the same letter, so each can only hold letters all of them allow.
+fn tie_masks(masks: CellMasks, ties: &GenericMatrix<MatrixIndex>) -> CellMasks {
+    let mut res = masks;
+    let all_cells = || RowIndex::all_values().flat_map(|row| ColIndex::all_values().map(move |col| MatrixIndex{row, col}));
+    for mi in all_cells() {
+        res[ties[mi]] = res[ties[mi]].and(masks[mi]);
+    }
+    for mi in all_cells() {
+        res[mi] = res[ties[mi]];
+    }
+    res
+}
+
+// Reads one row of a template. Each cell is a letter, & for any letter, # for a block, or a class as in a glob: a set of letters like [aeiou], everything but a set like [^xyz], or [:V:] or [:C:] for any vowel or consonant. Cells with a class are blank, with the class as their mask.
+fn parse_template_cells(row: &str) -> Result<Vec<(EncodedChar, Option<CharSet>)>, String> {
+    let mut res = Vec::new();
+    let mut chars = row.chars();
+    while let Some(c) = chars.next() {
+        res.push(match c {
+            '#' => (BLOCK_CHAR, None),
+            '[' => (NULL_CHAR, Some(super::super::glob::parse_class(&mut chars)?)),
+            c => (c.try_into().map_err(|_| format!("{:?} is not a valid char", c))?, None),
+        });
+    }
+    Ok(res)
+}
+
+pub fn make_templates(
+    must_use: &[(EitherWord, Placement)],
+    from_templates: Vec<WordMatrix>,
+) -> Vec<WordMatrix> {
+    let (&(current_word, placement), rest) = if let Some(v) = must_use.split_last() { v } else { return from_templates };
+    let mut to_templates = Vec::new();
+    each_dimension!(dim, {
+        let is_rows = dim::DIMENSION_ID == dim_row::DIMENSION_ID;
+        let allowed = |i: dim::Index| match placement {
+            Placement::Anywhere => true,
+            Placement::Across => is_rows,
+            Placement::Down => !is_rows,
+            Placement::Row(n) => is_rows && i.into():usize == n,
+            Placement::Col(n) => !is_rows && i.into():usize == n,
+        };
+        if let Some(word) = dim::get_from_either(current_word) {
+            for template in &from_templates {
+                for i in dim::Index::all_values() {
+                    if allowed(i) && word.is_match(dim::index_matrix(*template, i)) {
+                        let mut new_matrix = *template;
+                        dim::set_matrix(&mut new_matrix, i, word);
+                        to_templates.push(new_matrix);
+                    }
+                }
+            }
+        }
+    });
+    make_templates(rest, to_templates)
+}
+
+pub fn outer_compute(
+    wordlist: TheSet<EitherWord>,
+    col_wordlist: Option<TheSet<EitherWord>>,
+    templates: &[WordMatrix],
+    // One for each template, or None when no template has a class.
+    cell_masks: Option<&[CellMasks]>,
+    num_threads: usize,
+    output_func: impl 'static + Send + FnOnce(std::sync::mpsc::Receiver<WordMatrix>) -> Result<(), std::io::Error>,
+    show_progress: bool,
+    count_rects: bool,
+    top: Option<Arc<TopK>>,
+    restrictions: Restrictions,
+    strategy: Strategy,
+) -> u64 {
+    let invariants = restrictions.invariants();
+    // A symmetric search already copies cells across the diagonal, but cells tied by other symmetries are tied across it too.
+    let ties = if restrictions.invariant_under.is_empty() { None } else { Some(make_ties(&invariants)) };
+    let Restrictions{distinct, symmetric, canonical, symmetry_group, ..} = restrictions.clone();
+    let diagonals = restrictions.diagonal_lines();
+    #[cfg(feature = "serial")]
+    let prefix_map = SerialPrefixMaps::new(&make_prefix_map(WordMatrix::default(), &wordlist, col_wordlist.as_ref(), &restrictions).2);
+    #[cfg(feature = "serial")]
+    let prefix_map_arc = Arc::new(prefix_map);
+
+    let wordlist_arc = Arc::new(wordlist);
+    let col_wordlist_arc = col_wordlist.map(Arc::new);
+    let templates_arc:Arc<Vec<WordMatrix>> = Arc::new(templates.to_vec());
+    let cell_masks_arc:Option<Arc<Vec<CellMasks>>> = cell_masks.map(|m| Arc::new(m.to_vec()));
+
+    // Of each set of rects that are the same but for these symmetries, only the one that comes first is kept. Whether a flipped or transposed result is also a result has to be checked for each one, unless the wordlists and templates guarantee it, in which case it's safe to cut off a branch as soon as it can't come first.
+    let symmetries:Vec<Symmetry> = Symmetry::all()
+        .filter(|&s| symmetry_group || (canonical && s == Symmetry::TRANSPOSE))
+        .collect();
+    let (rows_reversible, cols_reversible) = {
+        let wordlists = (&*wordlist_arc, col_wordlist_arc.as_deref().unwrap_or(&wordlist_arc));
+        each_dimension!(dim, {
+            dim::index_tuple(&wordlists).iter().filter_map(|&w| dim::get_from_either(w)).all(|w| {
+                dim::index_tuple(&wordlists).contains(&w.reversed().into())
+            })
+        })
+    };
+    let shared_wordlist = col_wordlist_arc.as_deref().map_or(true, |c| *c == *wordlist_arc);
+    let pruning_symmetries:Vec<Symmetry> = symmetries.iter().copied().filter(|&s| {
+        (!s.flip_cols || rows_reversible)
+            && (!s.flip_rows || cols_reversible)
+            && (!s.transpose || shared_wordlist)
+            // Flipping a rect that's the same under some symmetry only gives one that's the same under it too when the two commute.
+            && invariants.iter().all(|&i| s.commutes_with(i))
+            // A flip or transpose can move one diagonal onto the other, or read it backwards.
+            && diagonals.iter().all(|line| {
+                let image:Vec<MatrixIndex> = line.iter().map(|&mi| s.source(mi)).collect();
+                diagonals.iter().any(|other| *other == image || (rows_reversible && other.iter().rev().eq(image.iter())))
+            })
+            // A flip or transpose moves each row and column's patterns elsewhere, and may read them backwards.
+            && restrictions.line_patterns.0.is_empty()
+            && restrictions.line_patterns.1.is_empty()
+            && templates.iter().enumerate().all(|(i, t)| templates.iter().enumerate().any(|(j, u)| {
+                *u == s.apply(t) && cell_masks.map_or(true, |masks| masks[j] == s.apply(&masks[i]))
+            }))
+    }).collect();
+    let (count_tx, count_rx) = crossbeam_channel::bounded::<u64>(2);
+    // "w2m" => worker threads to output thread
+    let (w2m_tx, w2m_rx) = std::sync::mpsc::sync_channel(4);
+    let output_thread = std::thread::spawn(move || output_func(w2m_rx));
+    let count_thread = std::thread::spawn(move || {
+        let mut count = 0;
+        while let Ok(msg) = count_rx.recv() {
+            count += msg;
+        }
+        count
+    });
+    for (template_idx, template) in templates.iter().enumerate() {
+        let masks = cell_masks.map(|m| m[template_idx]);
+        #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+        let (_row_counts, _col_counts, prefix_map) = make_prefix_map(*template, &wordlist_arc, col_wordlist_arc.as_deref(), &restrictions);
+
+        // "m2w" => main thread to worker threads
+        let (m2w_tx, m2w_rx) = crossbeam_channel::bounded::<WordMatrix>(2);
+        let (prog_tx, prog_rx) = crossbeam_channel::bounded::<()>(2);
+        let mut worker_handles = Vec::new();
+
+        #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+        let prefix_map_arc = Arc::new(prefix_map);
+        #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+        let line_masks_arc = strategy.lookahead.is_some().then(|| {
+            Arc::new(make_line_masks(*template, &wordlist_arc, col_wordlist_arc.as_deref(), &restrictions))
+        });
+        let bounds_arc = top.as_ref().map(|top| {
+            Arc::new(make_score_bounds(*template, &wordlist_arc, col_wordlist_arc.as_deref(), &top.scores))
+        });
+        let queue_arc = Arc::new(WorkQueue::new(num_threads));
+
+        for me in 0..num_threads {
+            let rxc = m2w_rx.clone();
+            let my_queue = Arc::clone(&queue_arc);
+            let txc = w2m_tx.clone();
+            let countc = count_tx.clone();
+            let progc = prog_tx.clone();
+            let my_prefix_map = Arc::clone(&prefix_map_arc);
+            let my_wordlists = (
+                Arc::clone(&wordlist_arc),
+                col_wordlist_arc.clone().unwrap_or_else(|| Arc::clone(&wordlist_arc)),
+            );
+            let my_top = top.clone().zip(bounds_arc.clone());
+            let my_templates = Arc::clone(&templates_arc);
+            let my_cell_masks = cell_masks_arc.clone();
+            let my_symmetries = symmetries.clone();
+            let my_pruning_symmetries = pruning_symmetries.clone();
+            let my_invariants = invariants.clone();
+            let my_diagonals = diagonals.clone();
+            let my_line_patterns = restrictions.line_patterns.clone();
+            let my_lookahead = strategy.lookahead.clone();
+            #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+            let my_line_masks = line_masks_arc.clone();
+            #[allow(unused_variables)]
+            let dynamic_order = strategy.dynamic_order;
+            worker_handles.push(
+                std::thread::spawn( move || {
+                    let mut thread_count = 0;
+                    let mut on_result = |a:WordMatrix| {
+                        let wordlists = (&*my_wordlists.0, &*my_wordlists.1);
+                        if !words_valid(&a, wordlists) || !diagonals_valid(&a, wordlists.0, &my_diagonals) {
+                            return
+                        }
+                        if my_symmetries.iter().any(|s| {
+                            let image = s.apply(&a);
+                            image < a && is_result(&image, wordlists, &my_templates, my_cell_masks.as_deref().map(Vec::as_slice), &my_invariants, &my_diagonals, &my_line_patterns)
+                        }) {
+                            return
+                        }
+                        if let Some((top, _)) = &my_top {
+                            top.offer(a);
+                        } else if count_rects {
+                            thread_count += 1;
+                        } else {
+                            txc.send(a).unwrap();
+                        }
+                    };
+                    // Steps, steps cut off and lookups, for my_lookahead.
+                    let lookahead_counts = std::cell::Cell::new((0u64, 0u64, 0u64));
+                    #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+                    let look_ahead = |m:&WordMatrix, at_idx| {
+                        let mut lookups = 0;
+                        let ok = lines_can_finish(&my_prefix_map, my_line_masks.as_deref().unwrap(), m, at_idx, masks.as_ref(), &mut lookups);
+                        let (steps, cut, total_lookups) = lookahead_counts.get();
+                        lookahead_counts.set((steps + 1, cut + (!ok as u64), total_lookups + lookups));
+                        ok
+                    };
+                    // --lookahead is turned down for the serial prefix map before it gets this far.
+                    #[cfg(feature = "serial")]
+                    let look_ahead = |_:&WordMatrix, _| true;
+                    let checks = |m:&WordMatrix, at_idx| {
+                        (!distinct || no_repeated_words(m, at_idx))
+                            && my_pruning_symmetries.iter().all(|s| s.comes_first(m))
+                            && my_top.as_ref().map_or(true, |(top, bounds)| top.may_improve(bounds, m))
+                            && (my_lookahead.is_none() || look_ahead(m, at_idx))
+                    };
+                    let needs_checks = my_top.is_some() || distinct || !my_pruning_symmetries.is_empty() || my_lookahead.is_some();
+                    let share = Some((&*my_queue, me));
+                    while let Some((msg, from_main)) = my_queue.next(me, &rxc) {
+                        let target_idx = MatrixIndex{row: RowIndex::MAX, col: ColIndex::MAX};
+                        #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+                        if dynamic_order {
+                            if needs_checks {
+                                compute_dynamic(&my_prefix_map, msg, masks.as_ref(), share, checks, &mut on_result);
+                            } else {
+                                compute_dynamic(&my_prefix_map, msg, masks.as_ref(), share, |_, _| true, &mut on_result);
+                            }
+                            my_queue.finish();
+                            if show_progress && from_main {
+                                progc.send(()).unwrap();
+                            }
+                            continue;
+                        }
+                        // Separate calls so the usual search doesn't pay for checks or mirroring it doesn't need.
+                        match (symmetric, needs_checks) {
+                            (false, false) => compute::<false,_,_>(&my_prefix_map, msg, target_idx, ties.as_ref(), masks.as_ref(), share, |_, _| true, &mut on_result),
+                            (false, true) => compute::<false,_,_>(&my_prefix_map, msg, target_idx, ties.as_ref(), masks.as_ref(), share, checks, &mut on_result),
+                            (true, false) => compute::<true,_,_>(&my_prefix_map, msg, target_idx, ties.as_ref(), masks.as_ref(), share, |_, _| true, &mut on_result),
+                            (true, true) => compute::<true,_,_>(&my_prefix_map, msg, target_idx, ties.as_ref(), masks.as_ref(), share, checks, &mut on_result),
+                        }
+                        my_queue.finish();
+                        // Only the main thread's jobs count towards the progress bar. Any split off from them are counted with them, if a bit early.
+                        if show_progress && from_main {
+                            progc.send(()).unwrap();
+                        }
+                    }
+                    if count_rects {
+                        countc.send(thread_count).unwrap();
+                    }
+                    if let Some(totals) = &my_lookahead {
+                        let (steps, cut, lookups) = lookahead_counts.get();
+                        totals.steps.fetch_add(steps, atomic::Ordering::Relaxed);
+                        totals.cut.fetch_add(cut, atomic::Ordering::Relaxed);
+                        totals.lookups.fetch_add(lookups, atomic::Ordering::Relaxed);
+                    }
+                })
+            );
+        }
+
+        let a = &*prefix_map_arc;
+        // How many jobs the main thread would send, handing off at `mi`.
+        let count_jobs = |mi| {
+            let mut count:usize = 0;
+            if symmetric {
+                compute::<true,_,_>(a, *template, mi, ties.as_ref(), masks.as_ref(), None, |_, _| true, |_| count += 1);
+            } else {
+                compute::<false,_,_>(a, *template, mi, ties.as_ref(), masks.as_ref(), None, |_, _| true, |_| count += 1);
+            }
+            count
+        };
+        //If there's only one worker thread, and we don't need to show progress, then there's no point "splitting up" the work
+        let split_at = if worker_handles.len() == 1 && !show_progress {
+            None
+        } else {
+            let depth = strategy.split_depth.unwrap_or_else(|| pick_split_depth(template, worker_handles.len(), count_jobs));
+            split_point(template, depth)
+        };
+        if DEBUG { dbg!(split_at); }
+
+        let progress_bar_thread = if show_progress {
+            let count = split_at.map_or(1, count_jobs);
+            let mut progress_bar = BernoulliBar::with_goal(count).timed();
+            eprintln!("{}", progress_bar);
+            let mut last_progress_display = std::time::Instant::now();
+            Some(std::thread::spawn(move || {
+                while let Ok(_) = prog_rx.recv() {
+                    progress_bar.add(true);
+                    if last_progress_display.elapsed().as_secs() >= 1 {
+                        last_progress_display = std::time::Instant::now();
+                        eprintln!("{}", progress_bar);
+                    }
+                }
+            }))
+        } else { None };
+
+        let f = |ca| {
+            if DEBUG { dbg!(ca); }
+            queue_arc.send(&m2w_tx, ca);
+            if show_progress {
+            }
+        };
+        if DEBUG { dbg!(); }
+        match split_at {
+            None => f(*template),
+            Some(mi) if symmetric => compute::<true,_,_>(
+                a,
+                *template,
+                mi,
+                ties.as_ref(),
+                masks.as_ref(),
+                None,
+                |_, _| true,
+                f,
+            ),
+            Some(mi) => compute::<false,_,_>(
+                a,
+                *template,
+                mi,
+                ties.as_ref(),
+                masks.as_ref(),
+                None,
+                |_, _| true,
+                f,
+            ),
+        }
+        if DEBUG { dbg!(); }
+
+        queue_arc.close();
+        drop(m2w_tx);
+        drop(prog_tx);
+        for h in worker_handles {
+            h.join().unwrap();
+        }
+        if let Some(t) = progress_bar_thread { t.join().unwrap() }
+        if DEBUG { dbg!(); }
+    }
+    drop(count_tx);
+    let full_count:u64 = count_thread.join().unwrap();
+    if DEBUG { dbg!(); }
+    if let Some(top) = &top {
+        for m in top.take_best() {
+            w2m_tx.send(m).unwrap();
+        }
+    }
+    drop(w2m_tx);
+    output_thread.join().unwrap().unwrap();
+    if DEBUG { dbg!(); }
+    full_count
+}
+
+// The `depth`th empty cell of the template, which the main thread fills in up to before handing off to the worker threads, or None to hand off the template as it is. Deeper than the template has empty cells, it's the last cell.
+fn split_point(template: &WordMatrix, depth: usize) -> Option<MatrixIndex> {
+    if depth == 0 { return None }
+    let mut nulls_so_far = 0;
+    let mut mi = MatrixIndex::ZERO;
+    loop {
+        if template[mi] == NULL_CHAR { nulls_so_far += 1 }
+        if nulls_so_far == depth { return Some(mi) }
+        mi = match mi.inc() {
+            Some(v) => v,
+            None => return Some(mi),
+        }
+    }
+}
+
+// Work stealing evens out jobs of different sizes, so each thread only needs a few to start with.
+const JOBS_PER_THREAD:usize = 16;
+
+// The split depth used when Strategy::split_depth isn't given: the shallowest that gives every thread JOBS_PER_THREAD jobs, going by `count_jobs` at each split_point. Each depth has at most CHAR_SET_SIZE times as many jobs as the one before, so counting them never takes long next to the search itself.
+fn pick_split_depth(template: &WordMatrix, num_threads: usize, mut count_jobs: impl FnMut(MatrixIndex) -> usize) -> usize {
+    let empty_cells = template.0.iter().filter(|&&c| c == NULL_CHAR).count();
+    let mut depth = 0;
+    while depth < empty_cells {
+        depth += 1;
+        let jobs = count_jobs(split_point(template, depth).unwrap());
+        // No jobs means nothing to search, at any depth.
+        if jobs == 0 || jobs >= num_threads * JOBS_PER_THREAD { break }
+    }
+    depth
+}
+
+// It is assumed that this function does *not* need to be fast, and should be written in whatever way is reasonably fast and most correct and elegant.
+pub fn make_prefix_map(
+    template: WordMatrix,
+    wordlist: &TheSet<EitherWord>,
+    col_wordlist: Option<&TheSet<EitherWord>>,
+    restrictions: &Restrictions,
+) -> (usize, usize, WordPrefixMap) {
+    // Squares normally use the rows map for the columns too, but that only works when they share a wordlist.
+    let share_maps = WORD_SQUARE_WIDTH == WORD_SQUARE_HEIGHT && col_wordlist.is_none();
+    let wordlists = (wordlist, col_wordlist.unwrap_or(wordlist));
+    let mut word_counts = [0usize; 2];
+    let mut res:WordPrefixMap = Default::default();
+    let mut word_templates = (vec![], vec![]);
+    each_dimension!(dim, {
+        let my_templates = dim::index_tuple_mut(&mut word_templates);
+        for i in dim::Index::all_values() {
+            let word = dim::index_matrix(template, i);
+            if word.has_blocks() {
+                my_templates.extend(word.slots());
+            } else {
+                my_templates.push(word);
+            }
+        }
+        my_templates.sort();
+        my_templates.dedup();
+    });
+    if_square! {
+        if share_maps {
+            for el in &word_templates.1 {
+                word_templates.0.push(*el);
+            }
+            word_templates.0.sort();
+            word_templates.0.dedup();
+        }
+    }
+    each_dimension!(dim, {
+        // Fills in a map from the words that match every glob, and gives how many there were.
+        let fill_map = |map: &mut TheMap<dim::Word,CharSet>, word_templates: &[dim::Word], globs: &[&Glob]| {
+            let mut count = 0;
+            // A single cell between blocks isn't a word that way, so it can be any letter.
+            for &template in word_templates {
+                if template.is_single_cell() && template.0[0] == NULL_CHAR {
+                    map.insert(template, CharSet::full());
+                }
+            }
+            for &w in *dim::index_tuple(&wordlists) {
+                if let Some(w) = dim::get_from_either(w) {
+                    if !globs.iter().all(|g| g.matches(&w.0)) { continue }
+                    count += 1;
+                    for c in &*w { assert_ne!(*c, NULL_CHAR); }
+                    // Words for slots between blocks are in the wordlist padded out, and only fill slots of the same length.
+                    for &template in word_templates {
+                        if template.is_match(w) {
+                            let p = w.prefixes(template);
+                            for (prefix,c) in p {
+                                map.entry(prefix).or_default().set(c);
+                            }
+                        }
+                    }
+                }
+            }
+            count
+        };
+        if !(share_maps && dim::DIMENSION_ID == dim_col::DIMENSION_ID) {
+            word_counts[dim::DIMENSION_ID] = fill_map(dim::prefix_map_mut(&mut res), dim::index_tuple(&word_templates).as_slice(), &[]);
+        }
+        // Rows and columns with patterns only get the words that match, so they need maps of their own.
+        for i in dim::Index::all_values() {
+            let globs:Vec<&Glob> = dim::index_tuple(&restrictions.line_patterns)
+                .iter()
+                .filter(|(line, _)| *line == i.into():usize)
+                .map(|(_, glob)| glob)
+                .collect();
+            if !globs.is_empty() {
+                let word = dim::index_matrix(template, i);
+                let line_templates:Vec<dim::Word> = if word.has_blocks() { word.slots().collect() } else { vec![word] };
+                fill_map(dim::line_prefix_map_mut(&mut res, i), &line_templates, &globs);
+            }
+        }
+    });
+    if_square! {
+        // A diagonal isn't filled in from one end, so its keys are what's filled in by the time each of its cells is reached, rather than prefixes.
+        let visits:Vec<MatrixIndex> = std::iter::successors(Some(MatrixIndex::ZERO), |mi| mi.inc())
+            .filter(|mi| !(restrictions.symmetric && mi.is_below_diagonal()))
+            .collect();
+        each_diagonal!(dim, {
+            if restrictions.wanted_diagonals()[dim::DIMENSION_ID - dim_diag::DIMENSION_ID] {
+                let word_template = dim::index_matrix(template);
+                let mut filled:Vec<bool> = word_template.iter().map(|&c| c != NULL_CHAR).collect();
+                let mut steps = Vec::new();
+                for &mi in &visits {
+                    let i = mi.row.into():usize;
+                    if dim::is_on_line(mi) && word_template.0[i] == NULL_CHAR {
+                        steps.push((i, filled.clone()));
+                    }
+                    for cell in [mi, mi.mirrored()] {
+                        if dim::is_on_line(cell) && (cell == mi || restrictions.symmetric) {
+                            filled[cell.row.into():usize] = true;
+                        }
+                    }
+                }
+                let map = dim::prefix_map_mut(&mut res);
+                for &w in wordlist {
+                    if let Some(w) = w.wide() {
+                        if word_template.is_match(w) {
+                            for (i, filled) in &steps {
+                                let mut key = w;
+                                for (c, &f) in key.iter_mut().zip(filled) {
+                                    if !f { *c = NULL_CHAR }
+                                }
+                                map.entry(key).or_default().set(w.0[*i]);
+                            }
+                        }
+                    }
+                }
+            }
+        });
+    }
+    let row_counts = word_counts[dim_row::DIMENSION_ID];
+    let col_counts = if share_maps { row_counts } else { word_counts[dim_col::DIMENSION_ID] };
+    (row_counts, col_counts, res)
+}
+
+// For one row or column of a template, the letters each of its cells can have going by the words that fit it, both on their own and given the letter in one other cell. Unlike the prefix map, these work whichever cells are filled in.
+#[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+#[derive(Debug,Clone)]
+pub struct LineMask {
+    len: usize,
+    // By cell.
+    alone: Vec<CharSet>,
+    // By the other cell, the letter in it, then the cell.
+    given: Vec<CharSet>,
+}
+
+#[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+impl LineMask {
+    fn new(len: usize) -> Self {
+        LineMask{
+            len,
+            alone: vec![CharSet::default(); len],
+            given: vec![CharSet::default(); len * CHAR_SET_SIZE * len],
+        }
+    }
+
+    fn given_idx(&self, other: usize, letter: usize, cell: usize) -> usize {
+        (other * CHAR_SET_SIZE + letter) * self.len + cell
+    }
+
+    // Adds a word that fits the slot starting at `start`.
+    fn add(&mut self, start: usize, word: &[EncodedChar]) {
+        for (i, &a) in word.iter().enumerate() {
+            self.alone[start + i].set(a);
+            for (j, &b) in word.iter().enumerate() {
+                let idx = self.given_idx(start + i, a.inner(), start + j);
+                self.given[idx].set(b);
+            }
+        }
+    }
+
+    // The letters that can go in cell `i`, given the letters in the line so far.
+    fn allowed(&self, line: impl Iterator<Item = EncodedChar>, i: usize) -> CharSet {
+        let mut res = self.alone[i];
+        for (j, e) in line.enumerate() {
+            if j != i && e != NULL_CHAR && e != BLOCK_CHAR {
+                res = res.and(self.given[self.given_idx(j, e.inner(), i)]);
+            }
+        }
+        res
+    }
+}
+
+// A LineMask for each row and column of a template, for Strategy::lookahead.
+#[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+#[derive(Debug,Clone)]
+pub struct LineMasks(Vec<LineMask>, Vec<LineMask>);
+
+#[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+impl LineMasks {
+    // The letters that can go in a cell going by just its row (or just its column), given what's in that so far.
+    fn at(&self, matrix: &WordMatrix, mi: MatrixIndex, across: bool) -> CharSet {
+        if across {
+            self.0[mi.row.into():usize].allowed(MatrixIndex::each_cell_in_row(mi.row).map(|c| matrix[c]), mi.col.into())
+        } else {
+            self.1[mi.col.into():usize].allowed(MatrixIndex::each_cell_in_col(mi.col).map(|c| matrix[c]), mi.row.into())
+        }
+    }
+}
+
+// Like make_prefix_map, this goes by the template's letters and blocks and by the globs for each line. Lines that are the same in both share the work.
+#[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+pub fn make_line_masks(
+    template: WordMatrix,
+    wordlist: &TheSet<EitherWord>,
+    col_wordlist: Option<&TheSet<EitherWord>>,
+    restrictions: &Restrictions,
+) -> LineMasks {
+    let wordlists = (wordlist, col_wordlist.unwrap_or(wordlist));
+    let (rows, cols) = each_dimension!(dim, {
+        let mut made:Vec<((dim::Word, Vec<&Glob>), LineMask)> = Vec::new();
+        let mut res = Vec::new();
+        for i in dim::Index::all_values() {
+            let word = dim::index_matrix(template, i);
+            let globs:Vec<&Glob> = dim::index_tuple(&restrictions.line_patterns)
+                .iter()
+                .filter(|(line, _)| *line == i.into():usize)
+                .map(|(_, glob)| glob)
+                .collect();
+            if let Some((_, mask)) = made.iter().find(|(key, _)| key.0 == word && key.1 == globs) {
+                res.push(mask.clone());
+                continue;
+            }
+            let len = word.0.len();
+            let mut mask = LineMask::new(len);
+            // Which slot each cell is in, by where it starts.
+            let slot_starts:Vec<Option<usize>> = (0..len)
+                .map(|p| (word.0[p] != BLOCK_CHAR).then(|| word.0[..p].iter().rposition(|&c| c == BLOCK_CHAR).map_or(0, |b| b + 1)))
+                .collect();
+            let words:Vec<dim::Word> = dim::index_tuple(&wordlists)
+                .iter()
+                .filter_map(|&w| dim::get_from_either(w))
+                .filter(|w| globs.iter().all(|g| g.matches(&w.0)))
+                .collect();
+            for start in (0..len).filter(|&p| slot_starts[p] == Some(p)) {
+                let slot = word.slot_at(start);
+                // A single cell between blocks isn't a word this way, so it's only limited by the template.
+                if slot.is_single_cell() {
+                    if slot.0[0] == NULL_CHAR {
+                        mask.alone[start] = CharSet::full();
+                    } else {
+                        mask.add(start, &slot.0[..1]);
+                    }
+                    continue;
+                }
+                let slot_len = slot.0.iter().position(|&c| c == BLOCK_CHAR).unwrap_or(len);
+                for w in &words {
+                    if slot.is_match(*w) {
+                        mask.add(start, &w.0[..slot_len]);
+                    }
+                }
+            }
+            // Cells in different slots don't limit each other.
+            for other in 0..len {
+                for cell in 0..len {
+                    if slot_starts[other].is_some() && slot_starts[other] != slot_starts[cell] {
+                        for letter in 0..CHAR_SET_SIZE {
+                            let idx = mask.given_idx(other, letter, cell);
+                            mask.given[idx] = CharSet::full();
+                        }
+                    }
+                }
+            }
+            made.push(((word, globs), mask.clone()));
+            res.push(mask);
+        }
+        res
+    });
+    LineMasks(rows, cols)
+}
+
+// The letters that fit in an empty cell, given the words through it so far.
+#[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+#[inline]
+fn cell_charset(prefix_map: &WordPrefixMap, matrix: WordMatrix, at_idx: MatrixIndex, has_blocks: bool) -> CharSet {
+    let (row_set, col_set) = each_dimension!(dim, {
+        let key = if has_blocks {
+            dim::get_slot_intersecting_point(matrix, at_idx)
+        } else {
+            dim::get_word_intersecting_point(matrix, at_idx)
+        };
+        dim::line_prefix_map(prefix_map, dim::get_my_index(at_idx)).get(&key).copied().unwrap_or_default()
+    });
+    #[allow(unused_mut)]
+    let mut charset = row_set.and(col_set);
+    if_square! {
+        each_diagonal!(dim, {
+            if let Some(map) = dim::prefix_map(prefix_map) {
+                if dim::is_on_line(at_idx) {
+                    charset = charset.and(map.get(&dim::index_matrix(matrix)).copied().unwrap_or_default());
+                }
+            }
+        });
+    }
+    charset
+}
+
+// Just the row's (or just the column's) half of cell_charset.
+#[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+#[inline]
+fn line_charset(prefix_map: &WordPrefixMap, matrix: WordMatrix, at_idx: MatrixIndex, across: bool, has_blocks: bool) -> CharSet {
+    let (row_set, col_set) = each_dimension!(dim, {
+        if across == (dim::DIMENSION_ID == dim_row::DIMENSION_ID) {
+            let key = if has_blocks {
+                dim::get_slot_intersecting_point(matrix, at_idx)
+            } else {
+                dim::get_word_intersecting_point(matrix, at_idx)
+            };
+            dim::line_prefix_map(prefix_map, dim::get_my_index(at_idx)).get(&key).copied()
+        } else { None }
+    });
+    row_set.or(col_set).unwrap_or_default()
+}
+
+// Whether some word in the prefix map finishes the row (or column) through the cells in `rest`, which are the empty cells left in it, in order, each with the letters allowed there.
+#[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+fn can_finish(prefix_map: &WordPrefixMap, mut matrix: WordMatrix, rest: &[(MatrixIndex, CharSet)], across: bool, has_blocks: bool, lookups: &mut u64) -> bool {
+    let ((mi, allowed), rest) = match rest.split_first() {
+        Some(v) => v,
+        None => return true,
+    };
+    *lookups += 1;
+    let mut letters = line_charset(prefix_map, matrix, *mi, across, has_blocks).and(*allowed);
+    while let Some(e) = letters.pop_first() {
+        matrix[*mi] = e;
+        if can_finish(prefix_map, matrix, rest, across, has_blocks, lookups) { return true }
+    }
+    false
+}
+
+// The lookahead for Strategy::lookahead, run just after `at_idx` is filled in. Each empty cell left in its row (or slot, with blocks) is limited to its mask and to what the column through it allows there: going by the prefix map once the column is filled in up to it, and by `line_masks` before that. The row has to be finishable with those. Then the same for its column.
+//
+// Any prefix in the map can be finished somehow, so the search through the words only goes as far as the last cell that's limited at all, and not at all when none are. How many lookups it took is added to `lookups`.
+#[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+fn lines_can_finish(prefix_map: &WordPrefixMap, line_masks: &LineMasks, matrix: &WordMatrix, at_idx: MatrixIndex, masks: Option<&CellMasks>, lookups: &mut u64) -> bool {
+    let has_blocks = matrix.0.contains(&BLOCK_CHAR);
+    let left:fn(MatrixIndex) -> Option<MatrixIndex> = |mi| mi.col.checked_sub(1).map(|col| MatrixIndex{row: mi.row, col});
+    let right:fn(MatrixIndex) -> Option<MatrixIndex> = |mi| mi.col.checked_add(1).map(|col| MatrixIndex{row: mi.row, col});
+    let up:fn(MatrixIndex) -> Option<MatrixIndex> = |mi| mi.row.checked_sub(1).map(|row| MatrixIndex{row, col: mi.col});
+    let down:fn(MatrixIndex) -> Option<MatrixIndex> = |mi| mi.row.checked_add(1).map(|row| MatrixIndex{row, col: mi.col});
+    // Whether every cell before this one going back along `step`, up to the nearest block, is filled in.
+    let filled_up_to = |mi: MatrixIndex, step: fn(MatrixIndex) -> Option<MatrixIndex>| {
+        std::iter::successors(step(mi), |&c| step(c))
+            .take_while(|&c| matrix[c] != BLOCK_CHAR)
+            .all(|c| matrix[c] != NULL_CHAR)
+    };
+    [(right, up, true), (down, left, false)].into_iter().all(|(next, cross_back, across)| {
+        let mut rest:Vec<(MatrixIndex, CharSet)> = Vec::new();
+        let mut limited_len = 0;
+        let mut cell = next(at_idx);
+        while let Some(mi) = cell {
+            if matrix[mi] == BLOCK_CHAR { break }
+            if matrix[mi] == NULL_CHAR {
+                let mut allowed = masks.map_or(CharSet::full(), |m| m[mi]);
+                if filled_up_to(mi, cross_back) {
+                    *lookups += 1;
+                    allowed = allowed.and(line_charset(prefix_map, *matrix, mi, !across, has_blocks));
+                } else {
+                    allowed = allowed.and(line_masks.at(matrix, mi, !across));
+                }
+                rest.push((mi, allowed));
+                if allowed != CharSet::full() {
+                    limited_len = rest.len();
+                }
+            }
+            cell = next(mi);
+        }
+        can_finish(prefix_map, *matrix, &rest[..limited_len], across, has_blocks, lookups)
+    })
+}
+
+// Like compute with no target, but fills in whichever open cell has the fewest letters that fit first, rather than going in order. Only cells whose row and column (or slot, with blocks) are filled in up to them are open, so every lookup is still a prefix (plus the template's letters) that the prefix map has.
+//
+// Each step is pushed onto a stack along with the letters it has left to try, and popped off again when it runs out, rather than stepping back one cell at a time. Symmetric searches, ties and diagonals all depend on the order cells are filled in, so they can't be used with this.
+//
+// When other threads want work, the letters left at the bottom of the stack are split off for them, as in compute.
+#[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+pub fn compute_dynamic<B: FnMut(&WordMatrix, MatrixIndex) -> bool, F: FnMut(WordMatrix)>(
+    prefix_map: &WordPrefixMap,
+    orig_matrix: WordMatrix,
+    masks: Option<&CellMasks>,
+    share: Option<(&WorkQueue, usize)>,
+    mut viable: B,
+    mut on_result: F,
+) {
+    let has_blocks = orig_matrix.0.contains(&BLOCK_CHAR);
+    // The last cell before each one in its row and in its column that the search fills in, if there's one before the nearest block. A cell is open once both are filled.
+    let mut waits_on:GenericMatrix<(Option<MatrixIndex>, Option<MatrixIndex>)> = Default::default();
+    let left:fn(MatrixIndex) -> Option<MatrixIndex> = |mi| mi.col.checked_sub(1).map(|col| MatrixIndex{row: mi.row, col});
+    let up:fn(MatrixIndex) -> Option<MatrixIndex> = |mi| mi.row.checked_sub(1).map(|row| MatrixIndex{row, col: mi.col});
+    let last_empty = |mi: MatrixIndex, step: fn(MatrixIndex) -> Option<MatrixIndex>| {
+        let mut cell = step(mi);
+        while let Some(c) = cell {
+            if orig_matrix[c] == BLOCK_CHAR { return None }
+            if orig_matrix[c] == NULL_CHAR { return Some(c) }
+            cell = step(c);
+        }
+        None
+    };
+    for row in RowIndex::all_values() {
+        for col in ColIndex::all_values() {
+            let mi = MatrixIndex{row, col};
+            waits_on[mi] = (last_empty(mi, left), last_empty(mi, up));
+        }
+    }
+    // Letters from the template or from the main thread's split haven't been checked yet. compute checks them as it steps over them, here they're checked once up front.
+    for row in RowIndex::all_values() {
+        for col in ColIndex::all_values() {
+            let mi = MatrixIndex{row, col};
+            if orig_matrix[mi] != NULL_CHAR && orig_matrix[mi] != BLOCK_CHAR && !viable(&orig_matrix, mi) {
+                return;
+            }
+        }
+    }
+    let mut matrix = orig_matrix;
+    let mut stack:Vec<(MatrixIndex, CharSet)> = Vec::with_capacity(WORD_SQUARE_SIZE);
+    loop {
+        let mut best:Option<(MatrixIndex, CharSet)> = None;
+        'pick: for row in RowIndex::all_values() {
+            for col in ColIndex::all_values() {
+                let mi = MatrixIndex{row, col};
+                let (left, up) = waits_on[mi];
+                let is_open = matrix[mi] == NULL_CHAR
+                    && left.map_or(true, |c| matrix[c] != NULL_CHAR)
+                    && up.map_or(true, |c| matrix[c] != NULL_CHAR);
+                if !is_open { continue }
+                let mut charset = cell_charset(prefix_map, matrix, mi, has_blocks);
+                if let Some(masks) = masks {
+                    charset = charset.and(masks[mi]);
+                }
+                if best.map_or(true, |(_, b)| charset.len() < b.len()) {
+                    best = Some((mi, charset));
+                    if charset.is_empty() { break 'pick }
+                }
+            }
+        }
+        match best {
+            Some(step) => stack.push(step),
+            // The first empty cell going row by row is always open, so there's none left.
+            None => on_result(matrix),
+        }
+        loop {
+            let (mi, letters) = match stack.last_mut() {
+                Some(top) => top,
+                None => return,
+            };
+            match letters.pop_first() {
+                Some(e) => {
+                    matrix[*mi] = e;
+                    if viable(&matrix, *mi) {
+                        if let Some((queue, me)) = share {
+                            if queue.wants_work() {
+                                split_off_dynamic(&mut stack, &matrix, orig_matrix, |job| queue.give(me, job));
+                            }
+                        }
+                        break
+                    }
+                },
+                None => {
+                    matrix[*mi] = orig_matrix[*mi];
+                    stack.pop();
+                },
+            }
+        }
+    }
+}
+
+// For compute: finds the earliest cell before `at_idx` that still has letters left to try, and hands each of them to `give` as a matrix filled in up to that cell, to be searched from there like any other job. They're taken out of that cell's charset so this search skips them. Cells that are tied to another one never branch, so they're never split.
+//
+// The letters handed off haven't been through `viable` yet, but compute checks every letter a job starts with again as it steps over it.
+fn split_off<const SYMMETRIC:bool>(
+    charset_array: &mut GenericMatrix<CharSet>,
+    matrix: &WordMatrix,
+    orig_matrix: WordMatrix,
+    at_idx: MatrixIndex,
+    ties: Option<&GenericMatrix<MatrixIndex>>,
+    mut give: impl FnMut(WordMatrix),
+) {
+    let mut job = orig_matrix;
+    let mut cell = Some(MatrixIndex::ZERO);
+    while let Some(mi) = cell {
+        if mi == at_idx { return }
+        if !(SYMMETRIC && mi.is_below_diagonal()) {
+            let branches = orig_matrix[mi] == NULL_CHAR && ties.map_or(true, |ties| ties[mi] == mi);
+            let mut rest = if branches { charset_array[mi].after(matrix[mi]) } else { CharSet::default() };
+            if !rest.is_empty() {
+                charset_array[mi] = charset_array[mi].without(rest);
+                while let Some(e) = rest.pop_first() {
+                    let mut split = job;
+                    split[mi] = e;
+                    if SYMMETRIC {
+                        split[mi.mirrored()] = e;
+                    }
+                    give(split);
+                }
+                return
+            }
+            job[mi] = matrix[mi];
+            if SYMMETRIC {
+                job[mi.mirrored()] = matrix[mi];
+            }
+        }
+        cell = mi.inc();
+    }
+}
+
+// For compute_dynamic: hands each letter left to try at the bottom-most step in `stack` that has any to `give`, as a matrix filled in up to and including that step.
+//
+// Like split_off, the letters handed off haven't been through `viable` yet, compute_dynamic checks the cells a job starts with before searching it.
+#[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+fn split_off_dynamic(
+    stack: &mut [(MatrixIndex, CharSet)],
+    matrix: &WordMatrix,
+    orig_matrix: WordMatrix,
+    mut give: impl FnMut(WordMatrix),
+) {
+    let mut job = orig_matrix;
+    for (mi, letters) in stack.iter_mut() {
+        if !letters.is_empty() {
+            while let Some(e) = letters.pop_first() {
+                let mut split = job;
+                split[*mi] = e;
+                give(split);
+            }
+            return
+        }
+        job[*mi] = matrix[*mi];
+    }
+}
+
+// In a symmetric search the cells below the diagonal are copies of the ones above, so they're stepped over.
+fn skip_below_diagonal<const SYMMETRIC:bool>(
+    mut idx: Option<MatrixIndex>,
+    step: fn(MatrixIndex) -> Option<MatrixIndex>,
+) -> Option<MatrixIndex> {
+    if SYMMETRIC {
+        while let Some(i) = idx {
+            if !i.is_below_diagonal() { break }
+            idx = step(i);
+        }
+    }
+    idx
+}
+
+// Partial matrices that `viable` turns down, given the cell that was just filled in, are skipped along with everything that would fill them in.
+//
+// With SYMMETRIC (squares only), only the cells on and above the diagonal are searched, and each is copied to its mirror as it's filled in.
+//
+// A cell in `ties` that's tied to another cell can only be a copy of it, so it's checked against the prefix map like any other cell but never branches.
+//
+// A cell's letters are also limited to its mask in `masks`, if given.
+//
+// With `share`, whenever the queue has threads waiting for work, the letters left to try at the earliest cell that has any are split off onto this thread's deque, see split_off.
+pub fn compute<'a, const SYMMETRIC:bool, B: FnMut(&WordMatrix, MatrixIndex) -> bool, F: FnMut(WordMatrix)>(
+    #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+    prefix_map: &WordPrefixMap,
+    #[cfg(feature = "serial")]
+    prefix_map: &'a SerialPrefixMaps,
+    orig_matrix: WordMatrix,
+    target_idx: MatrixIndex,
+    ties: Option<&GenericMatrix<MatrixIndex>>,
+    masks: Option<&CellMasks>,
+    share: Option<(&WorkQueue, usize)>,
+    mut viable: B,
+    mut on_result: F,
+) {
+    let mut at_idx = MatrixIndex::ZERO;
+    let mut charset_array:GenericMatrix<CharSet> = Default::default();
+    let mut is_nullish:GenericMatrix<bool> = GenericMatrix([true; WORD_SQUARE_SIZE]);
+    #[cfg(feature = "serial")]
+    let traversals_rows:GenericMatrix<Evil<'a>> = GenericMatrix([prefix_map.rows().top(); WORD_SQUARE_SIZE]);
+    #[cfg(feature = "serial")]
+    let traversals_cols:GenericMatrix<Evil<'a>> = GenericMatrix([prefix_map.cols().top(); WORD_SQUARE_SIZE]);
+    #[cfg(feature = "serial")]
+    let mut traversals = (traversals_rows, traversals_cols);
+    let mut matrix = orig_matrix;
+    // Blocks only come from templates, and mean looking up the slot a cell is in rather than its whole row and column.
+    #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+    let has_blocks = orig_matrix.0.contains(&BLOCK_CHAR);
+    let stop_at = skip_below_diagonal::<SYMMETRIC>(target_idx.inc(), MatrixIndex::inc);
+    let alphabet_len = alphabet_len();
+
+    for row in RowIndex::all_values() {
+        for col in ColIndex::all_values() {
+            let mi = MatrixIndex{row,col};
+            if orig_matrix[mi] != NULL_CHAR && orig_matrix[mi] != BLOCK_CHAR {
+                highly_unsafe_garuntee!(orig_matrix[mi].inner() < CHAR_SET_SIZE);
+                charset_array[mi].set(orig_matrix[mi]);
+            }
+        }
+    }
+
+    loop {
+        if DEBUG {
+            dbg!(at_idx,matrix[at_idx]);
+        }
+        if is_nullish[at_idx] {
+            #[cfg(feature = "serial")]
+            let (row_set, col_set) = each_dimension!(dim, {
+                if DEBUG { dbg!(dim::DIMENSION_ID); }
+                let traversal = dim::index_tuple_mut(&mut traversals);
+                let cur_evil = dim::back(at_idx).map(|mi| {
+                    #[cfg(feature = "unchecked")]
+                    let c = unsafe { CharSetRanged::new_unchecked(matrix[mi].inner()) };
+                    #[cfg(not(feature = "unchecked"))]
+                    let c = matrix[mi].inner().try_into().unwrap();
+                    unsafe {
+                        traversal[mi].get_unchecked(c)
+                    }
+                }).unwrap_or(dim::prefix_map(&prefix_map).top());
+                traversal[at_idx] = cur_evil;
+                if DEBUG { dbg!(cur_evil.line()); }
+                cur_evil.charset()
+            });
+            if orig_matrix[at_idx] == NULL_CHAR {
+                #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+                let mut charset = cell_charset(prefix_map, matrix, at_idx, has_blocks);
+                #[cfg(feature = "serial")]
+                let mut charset = row_set.and(col_set);
+                if let Some(masks) = masks {
+                    charset = charset.and(masks[at_idx]);
+                }
+                charset_array[at_idx] = charset;
+            }
+        }
+
+        if orig_matrix[at_idx] == NULL_CHAR || !is_nullish[at_idx] {
+            let next_char = match ties {
+                // The cell it's tied to always comes first, so it's already filled in.
+                Some(ties) if ties[at_idx] != at_idx => if is_nullish[at_idx] { Some(matrix[ties[at_idx]]) } else { None },
+                _ => matrix[at_idx].inc_below(alphabet_len),
+            };
+            match next_char {
+                Some(e) => matrix[at_idx] = e,
+                None => {
+                    matrix[at_idx] = orig_matrix[at_idx];
+                    if SYMMETRIC {
+                        matrix[at_idx.mirrored()] = orig_matrix[at_idx.mirrored()];
+                    }
+                    is_nullish[at_idx] = true;
+                    match skip_below_diagonal::<SYMMETRIC>(at_idx.dec(), MatrixIndex::dec) {
+                        Some(i) => {
+                            at_idx = i;
+                        },
+                        None => return,
+                    }
+                    continue;
+                }
+            }
+        }
+
+        is_nullish[at_idx] = false;
+        if SYMMETRIC {
+            matrix[at_idx.mirrored()] = matrix[at_idx];
+        }
+        let fits = matrix[at_idx] == BLOCK_CHAR || {
+            highly_unsafe_garuntee!(matrix[at_idx].inner() < CHAR_SET_SIZE);
+            charset_array[at_idx].has(matrix[at_idx])
+        };
+        if fits && viable(&matrix, at_idx) {
+            if let Some((queue, me)) = share {
+                if queue.wants_work() {
+                    split_off::<SYMMETRIC>(&mut charset_array, &matrix, orig_matrix, at_idx, ties, |job| queue.give(me, job));
+                }
+            }
+            let next = skip_below_diagonal::<SYMMETRIC>(at_idx.inc(), MatrixIndex::inc);
+            if next == stop_at {
+                if DEBUG { dbg!(); }
+                (&mut on_result)(matrix);
+            } else if let Some(i) = next {
+                at_idx = i;
+            } else {
+                #[cfg(not(feature = "unchecked"))]
+                unreachable!();
+                #[cfg(feature = "unchecked")]
+                unsafe { std::hint::unreachable_unchecked() };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    fn parse_matrix(str_a: &[&str]) -> WordMatrix {
+        let mut m = WordMatrix::default();
+        assert_eq!(str_a.len(), WORD_SQUARE_HEIGHT);
+        for rowi in RowIndex::all_values() {
+            let row_chars:Vec<_> = str_a[rowi.into():usize].chars().collect();
+            assert_eq!(row_chars.len(), WORD_SQUARE_WIDTH);
+            for coli in ColIndex::all_values() {
+                let mi = MatrixIndex{row: rowi, col: coli};
+                m[mi] = match row_chars[coli.into():usize] {
+                    '#' => BLOCK_CHAR,
+                    c => c.try_into().unwrap(),
+                };
+            }
+        }
+        m
+    }
+
+    // Collects everything outer_compute sends to the output thread, in the order it was sent.
+    fn collect_results(search: Search, num_threads: usize) -> Vec<WordMatrix> {
+        let results_mutex = Arc::new(Mutex::new(Vec::new()));
+        if DEBUG { dbg!(); }
+
+        let their_results_mutex = Arc::clone(&results_mutex);
+        search.outer_compute(
+            num_threads,
+            move |rx| {
+                let mut results_lock = their_results_mutex.lock().unwrap();
+                if DEBUG { dbg!(); }
+                while let Ok(ws) = rx.recv() { results_lock.push(ws); }
+                drop(results_lock);
+                drop(their_results_mutex);
+                if DEBUG { dbg!(); }
+                Ok(())
+            },
+            false,
+            false,
+        );
+        if DEBUG { dbg!(); }
+
+        let mut lock = results_mutex.lock().unwrap();
+        let mut results = Vec::new();
+        if DEBUG { dbg!(); }
+        std::mem::swap(&mut results, &mut lock);
+        results
+    }
+
+    // Searches the way `run` would with these options, and checks exactly the expected rects are found. With --top they also have to come out best first. Gives back the strategy it searched with, so its counts can be looked at.
+    #[allow(dead_code)]
+    pub fn assert_results(
+        options: &Options,
+        wordlist: &Wordlist,
+        col_wordlist: Option<&Wordlist>,
+        expected_results_str: &[&[&str]],
+    ) -> Strategy {
+        crate::echar::test_alphabet();
+        let mut expected_results:Vec<_> = expected_results_str.iter().map(|str_a| parse_matrix(str_a)).collect();
+        if DEBUG { dbg!(); }
+
+        let search = Search::new(options, wordlist, col_wordlist).expect("nothing to search");
+        let strategy = search.strategy.clone();
+        let mut results = collect_results(search, options.num_threads);
+
+        if options.top.is_none() {
+            expected_results.sort();
+            results.sort();
+        }
+        if DEBUG { dbg!(); }
+
+        assert_eq!(results, expected_results);
+        strategy
+    }
+
+    // Whether --lookahead lets a search with these options go on from the partial matrix `rows`, just after `at` was filled in, and the same with line masks that don't limit anything, to tell what the masks cut off.
+    #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+    #[allow(dead_code)]
+    pub fn lookahead_allows(options: &Options, wordlist: &Wordlist, rows: &[&str], at: (usize, usize)) -> (bool, bool) {
+        crate::echar::test_alphabet();
+        let search = Search::new(options, wordlist, None).expect("nothing to search");
+        let template = search.templates[0];
+        let (_, _, prefix_map) = make_prefix_map(template, &search.words, search.col_words.as_ref(), &search.restrictions);
+        let line_masks = make_line_masks(template, &search.words, search.col_words.as_ref(), &search.restrictions);
+        let unlimited = LineMasks(line_masks.0.iter().map(|m| unlimited_mask(m.len)).collect(), line_masks.1.iter().map(|m| unlimited_mask(m.len)).collect());
+        let matrix = parse_matrix(rows);
+        let at_idx = MatrixIndex{row: at.0.try_into().unwrap(), col: at.1.try_into().unwrap()};
+        let mut lookups = 0;
+        (
+            lines_can_finish(&prefix_map, &line_masks, &matrix, at_idx, None, &mut lookups),
+            lines_can_finish(&prefix_map, &unlimited, &matrix, at_idx, None, &mut lookups),
+        )
+    }
+
+    #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+    fn unlimited_mask(len: usize) -> LineMask {
+        let mut mask = LineMask::new(len);
+        mask.alone.fill(CharSet::full());
+        mask.given.fill(CharSet::full());
+        mask
+    }
+}
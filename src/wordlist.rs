@@ -0,0 +1,157 @@
+use core::convert::{TryFrom, TryInto};
+use core::str::FromStr;
+
+use fnv::FnvHashMap;
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+
+use crate::echar::*;
+
+#[derive(Debug,PartialEq,Eq)]
+pub enum WordConversionError {
+    WrongLength,
+    UnencodeableChar(usize, <EncodedChar as TryFrom<char>>::Error),
+    NullChar,
+}
+
+pub fn encode_str(s: &str, nulls_allowed: bool) -> Result<Vec<EncodedChar>, WordConversionError> {
+    let mut res = Vec::new();
+    for (i, c) in s.chars().enumerate() {
+        res.push(match c.try_into() {
+            Err(e) => return Err(WordConversionError::UnencodeableChar(i, e)),
+            Ok(v) if !nulls_allowed && v == NULL_CHAR => return Err(WordConversionError::NullChar),
+            Ok(v) => v,
+        });
+    }
+    Ok(res)
+}
+
+// Wordlists can spell the same letter precomposed (é) or decomposed (e + combining accent), NFC makes them all precomposed so they encode the same.
+pub fn normalize(s: &str) -> String {
+    s.nfc().collect()
+}
+
+// Maps é to e, ñ to n, etc. Only letters that decompose into one base letter plus combining marks are folded, so words never change length.
+pub fn fold_diacritics(s: &str) -> String {
+    s.chars().map(|c| {
+        let mut base = std::iter::once(c).nfd().filter(|&d| !is_combining_mark(d));
+        match (base.next(), base.next()) {
+            (Some(b), None) => b,
+            _ => c,
+        }
+    }).collect()
+}
+
+// The wordlist is read and encoded once, and then shared between every size being searched. Each size only needs to pick out the words of the lengths it uses.
+#[derive(Debug,Default)]
+pub struct Wordlist {
+    // Indexed by word length, each words' chars are stored one after the other.
+    by_length: Vec<Vec<EncodedChar>>,
+    // One score per word, in the same order as by_length. Words without a score count as 0.
+    scores_by_length: Vec<Vec<f64>>,
+    weighted: bool,
+    // How words were spelled before their diacritics were folded, for showing in the output. If several spellings fold to the same word, the first one is kept.
+    originals: FnvHashMap<Vec<EncodedChar>, String>,
+}
+
+impl Wordlist {
+    pub fn insert(&mut self, word: &[EncodedChar], score: Option<f64>) {
+        let len = word.len();
+        if self.by_length.len() <= len {
+            self.by_length.resize_with(len + 1, Default::default);
+            self.scores_by_length.resize_with(len + 1, Default::default);
+        }
+        self.by_length[len].extend_from_slice(word);
+        self.scores_by_length[len].push(score.unwrap_or(0.0));
+        self.weighted |= score.is_some();
+    }
+
+    // Whether any word was given a score.
+    pub fn is_weighted(&self) -> bool {
+        self.weighted
+    }
+
+    pub fn set_original(&mut self, word: &[EncodedChar], original: String) {
+        self.originals.entry(word.to_vec()).or_insert(original);
+    }
+
+    pub fn originals_of_length(&self, len: usize) -> impl Iterator<Item = (&[EncodedChar], &str)> {
+        self.originals.iter()
+            .filter(move |(word, _)| word.len() == len)
+            .map(|(word, original)| (word.as_slice(), original.as_str()))
+    }
+
+    pub fn words_of_length(&self, len: usize) -> impl Iterator<Item = &[EncodedChar]> {
+        let words = self.by_length.get(len).map(Vec::as_slice).unwrap_or_default();
+        // There are never any zero-length words, but chunks_exact panics on 0.
+        words.chunks_exact(len.max(1))
+    }
+
+    pub fn scored_words_of_length(&self, len: usize) -> impl Iterator<Item = (&[EncodedChar], f64)> {
+        let scores = self.scores_by_length.get(len).map(Vec::as_slice).unwrap_or_default();
+        self.words_of_length(len).zip(scores.iter().copied())
+    }
+}
+
+// How the scores of every word in a rectangle, across and down, make up the score of the rectangle.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum ScoreMode {
+    Sum,
+    Min,
+    Product,
+}
+
+impl ScoreMode {
+    pub fn combine(self, scores: impl IntoIterator<Item = f64>) -> f64 {
+        let scores = scores.into_iter();
+        match self {
+            ScoreMode::Sum => scores.sum(),
+            ScoreMode::Min => scores.fold(f64::INFINITY, f64::min),
+            ScoreMode::Product => scores.product(),
+        }
+    }
+}
+
+impl FromStr for ScoreMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sum" => Ok(ScoreMode::Sum),
+            "min" => Ok(ScoreMode::Min),
+            "product" => Ok(ScoreMode::Product),
+            _ => Err(format!("{:?} is not one of sum, min or product.", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalize_and_fold() {
+        let decomposed = "cafe\u{301}";
+        assert_eq!(decomposed.chars().count(), 5);
+        assert_eq!(normalize(decomposed), "café");
+        assert_eq!(fold_diacritics("café"), "cafe");
+        assert_eq!(fold_diacritics("Ñandú"), "Nandu");
+        // These don't decompose into a base letter, so they're left alone.
+        assert_eq!(fold_diacritics("øæ한"), "øæ한");
+    }
+
+    #[test]
+    fn scores() {
+        test_alphabet();
+        let mut wordlist = Wordlist::default();
+        wordlist.insert(&encode_str("ab", false).unwrap(), None);
+        assert!(!wordlist.is_weighted());
+        wordlist.insert(&encode_str("cd", false).unwrap(), Some(2.5));
+        assert!(wordlist.is_weighted());
+        let scores:Vec<f64> = wordlist.scored_words_of_length(2).map(|(_, score)| score).collect();
+        assert_eq!(scores, vec![0.0, 2.5]);
+
+        assert_eq!(ScoreMode::Sum.combine([1.0, 2.0, 4.0]), 7.0);
+        assert_eq!(ScoreMode::Min.combine([1.0, 2.0, 4.0]), 1.0);
+        assert_eq!(ScoreMode::Product.combine([1.0, 2.0, 4.0]), 8.0);
+    }
+}
@@ -1,15 +1,16 @@
-use core::convert::{TryFrom, TryInto};
+use core::convert::TryInto;
 use core::cmp::Ordering;
 use core::ops::{Index, IndexMut};
 use core::fmt;
 
 use fnv::FnvHashMap;
 
-use crate::config::*;
+use super::config::*;
 use crate::echar::*;
 #[cfg(feature = "serial")]
-use crate::serial_prefix_map::*;
-use crate::charset::CharSet;
+use super::serial_prefix_map::*;
+use super::super::charset::CharSet;
+use crate::wordlist::WordConversionError;
 
 #[derive(PartialEq,Eq,PartialOrd,Ord,Copy,Clone,Hash)]
 pub struct Word<const N:usize>(pub [EncodedChar; N]);
@@ -21,10 +22,51 @@ impl<const N:usize> fmt::Debug for Word<N> {
 }
 
 impl<const N:usize> Word<N> {
+    pub fn reversed(mut self) -> Self {
+        self.0.reverse();
+        self
+    }
+
     pub fn is_match(self, other: Self) -> bool {
         IntoIterator::into_iter(self.0.zip(other.0)).all(|(a,b)| a.is_match(b))
     }
 
+    // A word shorter than N, padded out with blocks, the way a slot between blocks is keyed in the prefix map.
+    pub fn padded(s: &[EncodedChar]) -> Self {
+        let mut res = Self([BLOCK_CHAR; N]);
+        res.0[..s.len()].copy_from_slice(s);
+        res
+    }
+
+    pub fn has_blocks(self) -> bool {
+        self.0.contains(&BLOCK_CHAR)
+    }
+
+    // The run of cells between blocks that position i is in, moved to the start and padded.
+    pub fn slot_at(self, i: usize) -> Self {
+        let start = self.0[..i].iter().rposition(|&c| c == BLOCK_CHAR).map_or(0, |b| b + 1);
+        let end = self.0[i..].iter().position(|&c| c == BLOCK_CHAR).map_or(N, |b| i + b);
+        Self::padded(&self.0[start..end])
+    }
+
+    // Every run of cells between blocks, as slot_at gives them, including single cells.
+    pub fn slots(self) -> impl Iterator<Item = Self> {
+        (0..N)
+            .filter(move |&i| self.0[i] != BLOCK_CHAR && (i == 0 || self.0[i - 1] == BLOCK_CHAR))
+            .map(move |i| self.slot_at(i))
+    }
+
+    // The letters of a slot, without the blocks it's padded out with.
+    pub fn unpadded(&self) -> &[EncodedChar] {
+        let len = self.0.iter().position(|&c| c == BLOCK_CHAR).unwrap_or(N);
+        &self.0[..len]
+    }
+
+    // Whether this slot is a single cell, which isn't a word in that direction.
+    pub fn is_single_cell(self) -> bool {
+        N == 1 || self.0[1] == BLOCK_CHAR
+    }
+
     pub fn prefixes(self, pattern: Self) -> Vec<(Self, EncodedChar)> {
         let mut mod_self = self;
         let mut res = Vec::new();
@@ -43,10 +85,15 @@ impl<const N:usize> Word<N> {
         res
     }
 
+    #[allow(dead_code)]
     pub fn as_slice(&self) -> &[EncodedChar] {
         self.0.as_slice()
     }
 
+    pub fn from_encoded(s: &[EncodedChar]) -> Self {
+        Self(s.try_into().unwrap())
+    }
+
     #[allow(dead_code)]
     pub fn from_str_with_nulls(s: &str) -> Result<Self, WordConversionError> {
         Self::from_str(s, true)
@@ -136,13 +183,6 @@ impl<const N:usize> core::ops::DerefMut for Word<N> {
     }
 }
 
-#[derive(Debug,PartialEq,Eq)]
-pub enum WordConversionError {
-    WrongLength,
-    UnencodeableChar(usize, <EncodedChar as TryFrom<char>>::Error),
-    NullChar,
-}
-
 // impl<const N:usize> TryFrom<&str> for Word<N> {
 //     type Error = WordConversionError;
 
@@ -165,11 +205,19 @@ pub enum WordConversionError {
 pub type TallWord = Word<WORD_SQUARE_HEIGHT>;
 pub type WideWord = Word<WORD_SQUARE_WIDTH>;
 
-#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
-pub enum EitherWord {
-    Tall(TallWord),
-    #[cfg(not(feature = "square"))]
-    Wide(WideWord),
+if_square! {
+    #[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+    pub enum EitherWord {
+        Tall(TallWord),
+    }
+}
+
+if_not_square! {
+    #[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+    pub enum EitherWord {
+        Tall(TallWord),
+        Wide(WideWord),
+    }
 }
 
 // #[cfg(feature = "square")]
@@ -201,10 +249,11 @@ impl From<TallWord> for EitherWord {
     }
 }
 
-#[cfg(not(feature = "square"))]
-impl From<WideWord> for EitherWord {
-    fn from(w: WideWord) -> Self {
-        Self::Wide(w)
+if_not_square! {
+    impl From<WideWord> for EitherWord {
+        fn from(w: WideWord) -> Self {
+            Self::Wide(w)
+        }
     }
 }
 
@@ -218,71 +267,75 @@ impl EitherWord {
     }
 }
 
-#[cfg(feature = "square")]
-impl EitherWord {
-    pub fn from_str(s: &str, nulls_allowed: bool) -> Result<Self, WordConversionError> {
-        Ok(Self::Tall(Word::from_str(s, nulls_allowed)?))
-    }
+if_square! {
+    impl EitherWord {
+        pub fn from_str(s: &str, nulls_allowed: bool) -> Result<Self, WordConversionError> {
+            Ok(Self::Tall(Word::from_str(s, nulls_allowed)?))
+        }
 
-    pub fn as_slice(&self) -> &[EncodedChar] {
-        match self {Self::Tall(v) => v.as_slice()}
-    }
+        #[allow(dead_code)]
+        pub fn as_slice(&self) -> &[EncodedChar] {
+            match self {Self::Tall(v) => v.as_slice()}
+        }
 
-    pub fn tall(self) -> Option<TallWord> {
-        Some(match self {Self::Tall(v) => v})
-    }
+        pub fn tall(self) -> Option<TallWord> {
+            Some(match self {Self::Tall(v) => v})
+        }
 
-    pub fn wide(self) -> Option<WideWord> {
-        Some(match self {Self::Tall(v) => v})
-    }
+        pub fn wide(self) -> Option<WideWord> {
+            Some(match self {Self::Tall(v) => v})
+        }
 
-    #[allow(dead_code)]
-    pub fn is_tall(self) -> bool { true }
+        #[allow(dead_code)]
+        pub fn is_tall(self) -> bool { true }
 
-    #[allow(dead_code)]
-    pub fn is_wide(self) -> bool { true }
+        #[allow(dead_code)]
+        pub fn is_wide(self) -> bool { true }
+    }
 }
 
-#[cfg(not(feature = "square"))]
-impl EitherWord {
-    pub fn from_str(s: &str, nulls_allowed: bool) -> Result<Self, WordConversionError> {
-        match TallWord::from_str(s, nulls_allowed) {
-            Ok(v) => return Ok(Self::from(v)),
-            Err(WordConversionError::WrongLength) => (),
-            Err(e) => return Err(e),
+if_not_square! {
+    impl EitherWord {
+        pub fn from_str(s: &str, nulls_allowed: bool) -> Result<Self, WordConversionError> {
+            match TallWord::from_str(s, nulls_allowed) {
+                Ok(v) => return Ok(Self::from(v)),
+                Err(WordConversionError::WrongLength) => (),
+                Err(e) => return Err(e),
+            }
+            WideWord::from_str(s, nulls_allowed).map(Self::from)
         }
-        WideWord::from_str(s, nulls_allowed).map(Self::from)
-    }
 
-    pub fn as_slice(&self) -> &[EncodedChar] {
-        match self {
-            Self::Tall(v) => v.as_slice(),
-            Self::Wide(v) => v.as_slice(),
+        #[allow(dead_code)]
+        pub fn as_slice(&self) -> &[EncodedChar] {
+            match self {
+                Self::Tall(v) => v.as_slice(),
+                Self::Wide(v) => v.as_slice(),
+            }
         }
-    }
 
-    pub fn tall(self) -> Option<TallWord> {
-        match self {
-            Self::Tall(v) => Some(v),
-            Self::Wide(_) => None,
+        pub fn tall(self) -> Option<TallWord> {
+            match self {
+                Self::Tall(v) => Some(v),
+                Self::Wide(_) => None,
+            }
         }
-    }
 
-    pub fn wide(self) -> Option<WideWord> {
-        match self {
-            Self::Tall(_) => None,
-            Self::Wide(v) => Some(v),
+        pub fn wide(self) -> Option<WideWord> {
+            match self {
+                Self::Tall(_) => None,
+                Self::Wide(v) => Some(v),
+            }
         }
-    }
 
-    #[allow(dead_code)]
-    pub fn is_tall(self) -> bool {
-        matches!(self, Self::Tall(_))
-    }
+        #[allow(dead_code)]
+        pub fn is_tall(self) -> bool {
+            matches!(self, Self::Tall(_))
+        }
 
-    #[allow(dead_code)]
-    pub fn is_wide(self) -> bool {
-        matches!(self, Self::Wide(_))
+        #[allow(dead_code)]
+        pub fn is_wide(self) -> bool {
+            matches!(self, Self::Wide(_))
+        }
     }
 }
 
@@ -308,18 +361,19 @@ impl Index<RowIndex> for TallWord {
     }
 }
 
-#[cfg(not(feature = "square"))]
-impl Index<ColIndex> for WideWord {
-    type Output = EncodedChar;
+if_not_square! {
+    impl Index<ColIndex> for WideWord {
+        type Output = EncodedChar;
 
-    fn index(&self, idx: ColIndex) -> &Self::Output {
-        let i:usize = idx.into();
-        #[cfg(feature = "unchecked")]
-        unsafe {
-            self.0.get_unchecked(i)
+        fn index(&self, idx: ColIndex) -> &Self::Output {
+            let i:usize = idx.into();
+            #[cfg(feature = "unchecked")]
+            unsafe {
+                self.0.get_unchecked(i)
+            }
+            #[cfg(not(feature = "unchecked"))]
+            self.0.get(i).unwrap()
         }
-        #[cfg(not(feature = "unchecked"))]
-        self.0.get(i).unwrap()
     }
 }
 
@@ -335,16 +389,17 @@ impl IndexMut<RowIndex> for TallWord {
     }
 }
 
-#[cfg(not(feature = "square"))]
-impl IndexMut<ColIndex> for WideWord {
-    fn index_mut(&mut self, idx: ColIndex) -> &mut Self::Output {
-        let i:usize = idx.into();
-        #[cfg(feature = "unchecked")]
-        unsafe {
-            self.0.get_unchecked_mut(i)
+if_not_square! {
+    impl IndexMut<ColIndex> for WideWord {
+        fn index_mut(&mut self, idx: ColIndex) -> &mut Self::Output {
+            let i:usize = idx.into();
+            #[cfg(feature = "unchecked")]
+            unsafe {
+                self.0.get_unchecked_mut(i)
+            }
+            #[cfg(not(feature = "unchecked"))]
+            self.0.get_mut(i).unwrap()
         }
-        #[cfg(not(feature = "unchecked"))]
-        self.0.get_mut(i).unwrap()
     }
 }
 
@@ -375,8 +430,44 @@ impl MatrixIndex {
         RowIndex::all_values().map(move |row| MatrixIndex{row, col})
     }
 
+    // Only meaningful in a square.
+    pub fn is_below_diagonal(self) -> bool {
+        self.row.into():usize > self.col.into():usize
+    }
+
+    // The cell on the other side of the diagonal. Only meaningful in a square.
+    pub fn mirrored(self) -> Self {
+        Self{
+            row: (self.col.into():usize).try_into().unwrap(),
+            col: (self.row.into():usize).try_into().unwrap(),
+        }
+    }
+
     #[cfg(not(feature = "weave"))]
     pub fn inc(self) -> Option<Self> {
+        self.inc_row_by_row()
+    }
+
+    #[cfg(not(feature = "weave"))]
+    pub fn dec(self) -> Option<Self> {
+        self.dec_row_by_row()
+    }
+
+    // Weaving goes back and forth across the diagonal, which only exists in a square.
+    #[cfg(feature = "weave")]
+    pub fn inc(self) -> Option<Self> {
+        if_square! { return self.inc_weave(); }
+        if_not_square! { return self.inc_row_by_row(); }
+    }
+
+    #[cfg(feature = "weave")]
+    pub fn dec(self) -> Option<Self> {
+        if_square! { return self.dec_weave(); }
+        if_not_square! { return self.dec_row_by_row(); }
+    }
+
+    #[cfg_attr(feature = "weave", allow(dead_code))]
+    pub fn inc_row_by_row(self) -> Option<Self> {
         if let Some(new_col) = self.col.checked_add(1) {
             return Some(Self{
                 row: self.row,
@@ -392,8 +483,8 @@ impl MatrixIndex {
         None
     }
     
-    #[cfg(not(feature = "weave"))]
-    pub fn dec(self) -> Option<Self> {
+    #[cfg_attr(feature = "weave", allow(dead_code))]
+    pub fn dec_row_by_row(self) -> Option<Self> {
         if let Some(new_col) = self.col.checked_sub(1) {
             return Some(Self{
                 row: self.row,
@@ -408,72 +499,75 @@ impl MatrixIndex {
         }
         None
     }
+}
 
-    #[cfg(feature = "weave")]
-    pub fn inc(self) -> Option<Self> {
-        if self.col >= self.row {
-            if let Some(new_col) = self.col.checked_add(1) {
-                return Some(Self{
-                    row: self.row,
-                    col: new_col,
-                })
-            }
-            if let Some(new_row) = self.row.checked_add(1) {
-                return Some(Self{
-                    row: new_row,
-                    col: (self.row.into():usize).try_into().unwrap(),
-                })
-            }
-            None
-        } else {
-            if let Some(new_row) = self.row.checked_add(1) {
-                return Some(Self{
-                    row: new_row,
-                    col: self.col,
-                })
-            }
-            if let Some(new_col) = self.col.checked_add(1) {
-                return Some(Self{
-                    row: (new_col.into():usize).try_into().unwrap(),
-                    col: new_col,
-                })
+#[cfg(feature = "weave")]
+if_square! {
+    impl MatrixIndex {
+        pub fn inc_weave(self) -> Option<Self> {
+            if self.col >= self.row {
+                if let Some(new_col) = self.col.checked_add(1) {
+                    return Some(Self{
+                        row: self.row,
+                        col: new_col,
+                    })
+                }
+                if let Some(new_row) = self.row.checked_add(1) {
+                    return Some(Self{
+                        row: new_row,
+                        col: (self.row.into():usize).try_into().unwrap(),
+                    })
+                }
+                None
+            } else {
+                if let Some(new_row) = self.row.checked_add(1) {
+                    return Some(Self{
+                        row: new_row,
+                        col: self.col,
+                    })
+                }
+                if let Some(new_col) = self.col.checked_add(1) {
+                    return Some(Self{
+                        row: (new_col.into():usize).try_into().unwrap(),
+                        col: new_col,
+                    })
+                }
+                None
             }
-            None
         }
-    }
 
-    #[cfg(feature = "weave")]
-    pub fn dec(self) -> Option<Self> {
-        if self.col >= self.row {
-            if self.col == self.row {
-                if let Some(new_col) = self.col.checked_sub(1) {
+        pub fn dec_weave(self) -> Option<Self> {
+            if self.col >= self.row {
+                if self.col == self.row {
+                    if let Some(new_col) = self.col.checked_sub(1) {
+                        Some(Self{
+                            row: RowIndex::MAX,
+                            col: new_col,
+                        })
+                    } else {
+                        None
+                    }
+                } else if let Some(new_col) = self.col.checked_sub(1) {
                     Some(Self{
-                        row: RowIndex::MAX,
+                        row: self.row,
                         col: new_col,
                     })
-                } else {
-                    None
-                }
-            } else if let Some(new_col) = self.col.checked_sub(1) {
-                Some(Self{
-                    row: self.row,
-                    col: new_col,
-                })
-            } else { unreachable!() }
-        } else {
-            if Some(self.row.into():usize) == self.col.checked_add(1).map(|a| a.into():usize) {
-                if let Some(new_row) = self.row.checked_sub(1) {
-                    Some(Self{
+                } else { unreachable!() }
+            } else {
+                if Some(self.row.into():usize) == self.col.checked_add(1).map(|a| a.into():usize) {
+                    if let Some(new_row) = self.row.checked_sub(1) {
+                        Some(Self{
+                            row: new_row,
+                            col: ColIndex::MAX,
+                        })
+                    } else { unreachable!() }
+                } else if let Some(new_row) = self.row.checked_sub(1) {
+                    return Some(Self{
                         row: new_row,
-                        col: ColIndex::MAX,
+                        col: self.col,
                     })
                 } else { unreachable!() }
-            } else if let Some(new_row) = self.row.checked_sub(1) {
-                return Some(Self{
-                    row: new_row,
-                    col: self.col,
-                })
-            } else { unreachable!() }
+            }
         }
     }
 }
@@ -591,7 +685,6 @@ pub mod dim_row {
         }
     }
 
-    #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
     pub fn get_my_index(mi: MatrixIndex) -> Index {
         mi.row
     }
@@ -601,7 +694,13 @@ pub mod dim_row {
         index_matrix(matrix, get_my_index(point))
     }
 
+    // Like get_word_intersecting_point, but only the slot between blocks that the point is in.
+    pub fn get_slot_intersecting_point(matrix: WordMatrix, point: MatrixIndex) -> Word {
+        get_word_intersecting_point(matrix, point).slot_at(cross::get_my_index(point).into())
+    }
+
     #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+    #[allow(dead_code)]
     pub fn prefix_map(map: &WordPrefixMap) -> &TheMap<Word,CharSet> {
         map.rows()
     }
@@ -615,6 +714,15 @@ pub mod dim_row {
         map.rows_mut()
     }
 
+    #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+    pub fn line_prefix_map(map: &WordPrefixMap, i: Index) -> &TheMap<Word,CharSet> {
+        map.row(i)
+    }
+
+    pub fn line_prefix_map_mut(map: &mut WordPrefixMap, i: Index) -> &mut TheMap<Word,CharSet> {
+        map.row_mut(i)
+    }
+
     pub fn index_tuple<T,U>(t: &(T, U)) -> &T {
         &t.0
     }
@@ -638,7 +746,8 @@ pub mod dim_row {
     }
 }
 
-#[cfg_attr(feature = "square", allow(dead_code))]
+// Most of this is unused for square sizes, where the row dimension stands in for both.
+#[allow(dead_code)]
 pub mod dim_col {
     use super::*;
     pub type Word = TallWord;
@@ -661,7 +770,6 @@ pub mod dim_col {
         }
     }
 
-    #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
     pub fn get_my_index(mi: MatrixIndex) -> Index {
         mi.col
     }
@@ -671,6 +779,11 @@ pub mod dim_col {
         index_matrix(matrix, get_my_index(point))
     }
 
+    // Like get_word_intersecting_point, but only the slot between blocks that the point is in.
+    pub fn get_slot_intersecting_point(matrix: WordMatrix, point: MatrixIndex) -> Word {
+        get_word_intersecting_point(matrix, point).slot_at(cross::get_my_index(point).into())
+    }
+
     #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
     pub fn prefix_map(map: &WordPrefixMap) -> &TheMap<Word,CharSet> {
         map.cols()
@@ -681,14 +794,17 @@ pub mod dim_col {
         map.cols()
     }
 
-    #[cfg(not(feature = "square"))]
     pub fn prefix_map_mut(map: &mut WordPrefixMap) -> &mut TheMap<Word,CharSet> {
         map.cols_mut()
     }
 
-    #[cfg(feature = "square")]
-    pub fn prefix_map_mut(_map: &mut WordPrefixMap) -> &mut TheMap<Word,CharSet> {
-        unreachable!()
+    #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+    pub fn line_prefix_map(map: &WordPrefixMap, i: Index) -> &TheMap<Word,CharSet> {
+        map.col(i)
+    }
+
+    pub fn line_prefix_map_mut(map: &mut WordPrefixMap, i: Index) -> &mut TheMap<Word,CharSet> {
+        map.col_mut(i)
     }
 
     pub fn index_tuple<T,U>(t: &(U, T)) -> &T {
@@ -714,6 +830,83 @@ pub mod dim_col {
     }
 }
 
+if_square! {
+    // The two diagonals of a square, for when they have to spell words too. Both are read top to bottom, so each letter's place in the word is its row, and both use the row wordlist. Unlike rows and columns, there's only one of each.
+    pub mod dim_diag {
+        use super::*;
+        pub type Word = WideWord;
+
+        pub const DIMENSION_ID:usize = 2;
+
+        pub fn cell(row: RowIndex) -> MatrixIndex {
+            MatrixIndex{row, col: (row.into():usize).try_into().unwrap()}
+        }
+
+        pub fn cells() -> impl Iterator<Item = MatrixIndex> {
+            RowIndex::all_values().map(cell)
+        }
+
+        pub fn is_on_line(mi: MatrixIndex) -> bool {
+            cell(mi.row) == mi
+        }
+
+        pub fn index_matrix(matrix: WordMatrix) -> Word {
+            let mut res:Word = Default::default();
+            for (i, mi) in cells().enumerate() {
+                res.0[i] = matrix[mi];
+            }
+            res
+        }
+
+        // None unless this diagonal has to spell a word.
+        #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+        pub fn prefix_map(map: &WordPrefixMap) -> Option<&TheMap<Word,CharSet>> {
+            map.inner_diag.as_ref()
+        }
+
+        pub fn prefix_map_mut(map: &mut WordPrefixMap) -> &mut TheMap<Word,CharSet> {
+            map.inner_diag.get_or_insert_with(Default::default)
+        }
+    }
+
+    pub mod dim_anti {
+        use super::*;
+        pub type Word = WideWord;
+
+        pub const DIMENSION_ID:usize = 3;
+
+        pub fn cell(row: RowIndex) -> MatrixIndex {
+            MatrixIndex{row, col: (WIDTH_MINUS_ONE - row.into():usize).try_into().unwrap()}
+        }
+
+        pub fn cells() -> impl Iterator<Item = MatrixIndex> {
+            RowIndex::all_values().map(cell)
+        }
+
+        pub fn is_on_line(mi: MatrixIndex) -> bool {
+            cell(mi.row) == mi
+        }
+
+        pub fn index_matrix(matrix: WordMatrix) -> Word {
+            let mut res:Word = Default::default();
+            for (i, mi) in cells().enumerate() {
+                res.0[i] = matrix[mi];
+            }
+            res
+        }
+
+        // None unless this diagonal has to spell a word.
+        #[cfg(any(feature = "fnvmap", feature = "btreemap"))]
+        pub fn prefix_map(map: &WordPrefixMap) -> Option<&TheMap<Word,CharSet>> {
+            map.inner_anti.as_ref()
+        }
+
+        pub fn prefix_map_mut(map: &mut WordPrefixMap) -> &mut TheMap<Word,CharSet> {
+            map.inner_anti.get_or_insert_with(Default::default)
+        }
+    }
+}
+
 // This little hack is extremely useful for IDE completions when using the each_dimension macros
 #[cfg(debug)]
 #[allow(dead_code)]
@@ -726,11 +919,30 @@ pub type TheMap<K, V> = std::collections::BTreeMap<K, V>;
 
 pub type TheSet<V> = fnv::FnvHashSet<V>;
 
-#[derive(Debug,Default)]
-pub struct WordPrefixMap {
-    inner_rows: TheMap<WideWord,CharSet>,
-    #[cfg(not(feature = "square"))]
-    inner_cols: TheMap<TallWord,CharSet>,
+if_square! {
+    #[derive(Debug,Default)]
+    pub struct WordPrefixMap {
+        inner_rows: TheMap<WideWord,CharSet>,
+        // Only when the columns have their own wordlist, otherwise the rows map stands in for both.
+        inner_cols: Option<TheMap<TallWord,CharSet>>,
+        // Only when the diagonals have to spell words.
+        inner_diag: Option<TheMap<WideWord,CharSet>>,
+        inner_anti: Option<TheMap<WideWord,CharSet>>,
+        // Rows and columns with their own word filters get their own maps, by index.
+        inner_line_rows: Vec<Option<TheMap<WideWord,CharSet>>>,
+        inner_line_cols: Vec<Option<TheMap<TallWord,CharSet>>>,
+    }
+}
+
+if_not_square! {
+    #[derive(Debug,Default)]
+    pub struct WordPrefixMap {
+        inner_rows: TheMap<WideWord,CharSet>,
+        inner_cols: TheMap<TallWord,CharSet>,
+        // Rows and columns with their own word filters get their own maps, by index.
+        inner_line_rows: Vec<Option<TheMap<WideWord,CharSet>>>,
+        inner_line_cols: Vec<Option<TheMap<TallWord,CharSet>>>,
+    }
 }
 
 impl WordPrefixMap {
@@ -740,32 +952,64 @@ impl WordPrefixMap {
 
     #[allow(dead_code)]
     pub fn cols(&self) -> &TheMap<TallWord,CharSet> {
-        #[cfg(not(feature = "square"))]
-        return &self.inner_cols;
-        #[cfg(feature = "square")]
-        return self.rows();
+        if_not_square! { return &self.inner_cols; }
+        if_square! { return self.inner_cols.as_ref().unwrap_or(&self.inner_rows); }
+    }
+
+    #[allow(dead_code)]
+    pub fn has_separate_cols(&self) -> bool {
+        if_not_square! { return true; }
+        if_square! { return self.inner_cols.is_some(); }
     }
 
     pub fn rows_mut(&mut self) -> &mut TheMap<WideWord,CharSet> {
         &mut self.inner_rows
     }
 
-    #[cfg(not(feature = "square"))]
     pub fn cols_mut(&mut self) -> &mut TheMap<TallWord,CharSet> {
-        &mut self.inner_cols
+        if_not_square! { return &mut self.inner_cols; }
+        if_square! { return self.inner_cols.get_or_insert_with(Default::default); }
+    }
+
+    // The map for just this row, which is the map for every row unless it has its own.
+    #[allow(dead_code)]
+    pub fn row(&self, i: RowIndex) -> &TheMap<WideWord,CharSet> {
+        self.inner_line_rows.get(i.into():usize).and_then(Option::as_ref).unwrap_or_else(|| self.rows())
+    }
+
+    #[allow(dead_code)]
+    pub fn col(&self, i: ColIndex) -> &TheMap<TallWord,CharSet> {
+        self.inner_line_cols.get(i.into():usize).and_then(Option::as_ref).unwrap_or_else(|| self.cols())
+    }
+
+    // Gives row i a map of its own, empty to start with.
+    pub fn row_mut(&mut self, i: RowIndex) -> &mut TheMap<WideWord,CharSet> {
+        let i = i.into():usize;
+        if self.inner_line_rows.len() <= i {
+            self.inner_line_rows.resize_with(i + 1, Default::default);
+        }
+        self.inner_line_rows[i].get_or_insert_with(Default::default)
+    }
+
+    pub fn col_mut(&mut self, i: ColIndex) -> &mut TheMap<TallWord,CharSet> {
+        let i = i.into():usize;
+        if self.inner_line_cols.len() <= i {
+            self.inner_line_cols.resize_with(i + 1, Default::default);
+        }
+        self.inner_line_cols[i].get_or_insert_with(Default::default)
     }
 }
 
-#[macro_export]
+// These are used from sibling modules within the same size module, hence `super::wordstuffs`.
 macro_rules! each_dimension {
     ($dim_name:ident, $block:expr) => {
         {
             let res1 = {
-                use $crate::wordstuffs::dim_row as $dim_name;
+                use super::wordstuffs::dim_row as $dim_name;
                 $block
             };
             let res2 = {
-                use $crate::wordstuffs::dim_col as $dim_name;
+                use super::wordstuffs::dim_col as $dim_name;
                 $block
             };
             (res1, res2)
@@ -773,19 +1017,21 @@ macro_rules! each_dimension {
     };
 }
 
-#[macro_export]
-macro_rules! each_unique_dimension {
-    ($dim_name:ident, $block:expr) => {
-        {
-            {
-                use $crate::wordstuffs::dim_row as $dim_name;
-                $block
-            };
-            #[cfg(not(feature = "square"))]
+if_square! {
+    // Like each_dimension, but for the diagonals of a square.
+    macro_rules! each_diagonal {
+        ($dim_name:ident, $block:expr) => {
             {
-                use $crate::wordstuffs::dim_col as $dim_name;
-                $block
-            };
-        }
-    };
-}
\ No newline at end of file
+                let res1 = {
+                    use super::wordstuffs::dim_diag as $dim_name;
+                    $block
+                };
+                let res2 = {
+                    use super::wordstuffs::dim_anti as $dim_name;
+                    $block
+                };
+                (res1, res2)
+            }
+        };
+    }
+}